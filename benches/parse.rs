@@ -0,0 +1,144 @@
+//! Request parsing benchmarks.
+//!
+//! `Value` benchmarks parse the same input without classifying it,
+//! so they show the overhead of turning parsed values into calls.
+//!
+//! `baseline` benchmarks parse the same input with the former `Peek` path,
+//! which tried every call as a notification, then as a method call
+//! and finally as a plain value, each time from a copy of the deserializer.
+#![feature(test)]
+
+extern crate test;
+extern crate serde;
+extern crate serde_json;
+extern crate jsonrpc_core;
+
+use test::Bencher;
+use jsonrpc_core::*;
+
+/// Former request deserialization, kept only to compare with.
+mod baseline {
+	use std::{mem, ptr};
+	use serde::de::{Deserialize, Deserializer};
+	use jsonrpc_core::{Call, MethodCall, Notification, Request, Value};
+
+	pub trait Peek: Sized {
+		fn peek<D>(deserializer: &mut D) -> Result<Self, D::Error> where D: Deserializer;
+	}
+
+	impl<T> Peek for T where T: Deserialize {
+		fn peek<D>(deserializer: &mut D) -> Result<Self, D::Error> where D: Deserializer {
+			unsafe {
+				let mut d: D = mem::uninitialized();
+				ptr::copy(deserializer, &mut d, 1);
+				let res = T::deserialize(&mut d);
+				if res.is_ok() {
+					mem::swap(deserializer, &mut d);
+				}
+				mem::forget(d);
+				res
+			}
+		}
+	}
+
+	pub struct PeekCall(pub Call);
+
+	impl Deserialize for PeekCall {
+		fn deserialize<D>(deserializer: &mut D) -> Result<PeekCall, D::Error>
+		where D: Deserializer {
+			Notification::peek(deserializer).map(Call::Notification)
+				.or_else(|_| MethodCall::peek(deserializer).map(Call::MethodCall))
+				.or_else(|_| Value::deserialize(deserializer).map(Call::Invalid))
+				.map(PeekCall)
+		}
+	}
+
+	pub struct PeekRequest(pub Request);
+
+	impl Deserialize for PeekRequest {
+		fn deserialize<D>(deserializer: &mut D) -> Result<PeekRequest, D::Error>
+		where D: Deserializer {
+			<Vec<PeekCall> as Peek>::peek(deserializer).map(|calls| Request::Batch(calls.into_iter().map(|call| call.0).collect()))
+				.or_else(|_| PeekCall::deserialize(deserializer).map(|call| Request::Single(call.0)))
+				.map(PeekRequest)
+		}
+	}
+}
+
+fn batch(size: usize, call: &Fn(usize) -> String) -> String {
+	format!("[{}]", (0..size).map(call).collect::<Vec<_>>().join(","))
+}
+
+fn method_call(i: usize) -> String {
+	format!(r#"{{"jsonrpc": "2.0", "method": "sum", "params": [{}, {}], "id": {}}}"#, i, i, i)
+}
+
+fn notification(i: usize) -> String {
+	format!(r#"{{"jsonrpc": "2.0", "method": "update", "params": {{"value": {}}}}}"#, i)
+}
+
+fn invalid(i: usize) -> String {
+	format!(r#"{{"jsonrpc": "2.0", "method": {}, "id": {}}}"#, i, i)
+}
+
+#[bench]
+fn parse_single_method_call(b: &mut Bencher) {
+	let request = method_call(1);
+	b.iter(|| serde_json::from_str::<Request>(&request).unwrap());
+}
+
+#[bench]
+fn parse_batch_of_1000_method_calls(b: &mut Bencher) {
+	let request = batch(1000, &method_call);
+	b.iter(|| serde_json::from_str::<Request>(&request).unwrap());
+}
+
+#[bench]
+fn parse_batch_of_1000_method_calls_as_value(b: &mut Bencher) {
+	let request = batch(1000, &method_call);
+	b.iter(|| serde_json::from_str::<Value>(&request).unwrap());
+}
+
+#[bench]
+fn parse_batch_of_1000_notifications(b: &mut Bencher) {
+	let request = batch(1000, &notification);
+	b.iter(|| serde_json::from_str::<Request>(&request).unwrap());
+}
+
+#[bench]
+fn parse_batch_of_1000_invalid_calls(b: &mut Bencher) {
+	let request = batch(1000, &invalid);
+	b.iter(|| serde_json::from_str::<Request>(&request).unwrap());
+}
+
+#[bench]
+fn baseline_single_method_call(b: &mut Bencher) {
+	let request = method_call(1);
+	b.iter(|| serde_json::from_str::<baseline::PeekRequest>(&request).unwrap());
+}
+
+#[bench]
+fn baseline_batch_of_1000_method_calls(b: &mut Bencher) {
+	let request = batch(1000, &method_call);
+	b.iter(|| serde_json::from_str::<baseline::PeekRequest>(&request).unwrap());
+}
+
+#[bench]
+fn baseline_batch_of_1000_notifications(b: &mut Bencher) {
+	let request = batch(1000, &notification);
+	b.iter(|| serde_json::from_str::<baseline::PeekRequest>(&request).unwrap());
+}
+
+#[bench]
+fn baseline_batch_of_1000_invalid_calls(b: &mut Bencher) {
+	let request = batch(1000, &invalid);
+	b.iter(|| serde_json::from_str::<baseline::PeekRequest>(&request).unwrap());
+}
+
+#[bench]
+fn handle_batch_of_1000_method_calls(b: &mut Bencher) {
+	let mut io = IoHandler::new();
+	io.add_typed_method("sum", |(a, b): (u64, u64)| -> Result<u64, Error> { Ok(a + b) });
+	let request = batch(1000, &method_call);
+	b.iter(|| io.handle_request(&request).unwrap());
+}
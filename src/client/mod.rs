@@ -0,0 +1,59 @@
+//! jsonrpc client side serialization
+use serde_json;
+use super::*;
+
+mod request_manager;
+
+pub use self::request_manager::{RequestManager, MatchError};
+
+/// Serializes outgoing request.
+///
+/// ```rust
+/// extern crate jsonrpc_core;
+/// use jsonrpc_core::*;
+/// use jsonrpc_core::client::*;
+///
+/// fn main() {
+/// 	let call = MethodCall::new("say_hello", Params::Array(vec![Value::U64(42)]), Id::Num(1));
+/// 	let request = Request::Single(Call::MethodCall(call));
+///
+/// 	assert_eq!(write_request(&request), r#"{"jsonrpc":"2.0","method":"say_hello","params":[42],"id":1}"#.to_string());
+/// }
+/// ```
+pub fn write_request(request: &Request) -> String {
+	// this should never fail
+	serde_json::to_string(request).unwrap()
+}
+
+/// Deserializes incoming response.
+pub fn read_response(response_str: &str) -> Result<Response, Error> {
+	serde_json::from_str(response_str).map_err(|_| Error::new(ErrorCode::ParseError))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::*;
+
+	#[test]
+	fn test_write_batch() {
+		let request = Request::Batch(vec![
+			Call::MethodCall(MethodCall::new("sum", Params::Array(vec![Value::U64(1), Value::U64(2)]), Id::Str("a".to_owned()))),
+			Call::Notification(Notification::new("update", Params::None))
+		]);
+
+		assert_eq!(write_request(&request), r#"[{"jsonrpc":"2.0","method":"sum","params":[1,2],"id":"a"},{"jsonrpc":"2.0","method":"update","params":[]}]"#.to_owned());
+	}
+
+	#[test]
+	fn test_read_response() {
+		let response = r#"{"jsonrpc":"2.0","result":3,"id":"a"}"#;
+
+		assert_eq!(read_response(response), Ok(Response::Single(Output::Success(Success {
+			jsonrpc: Version::V2,
+			result: Value::U64(3),
+			id: Id::Str("a".to_owned())
+		}))));
+		assert_eq!(read_response("{"), Err(Error::parse_error()));
+	}
+}
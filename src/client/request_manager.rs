@@ -0,0 +1,199 @@
+//! jsonrpc client response correlation
+use std::collections::{HashMap, HashSet};
+use super::super::*;
+
+/// Errors which may occur while matching responses with pending calls.
+#[derive(Debug, PartialEq)]
+pub enum MatchError {
+	/// There is no pending call with output id.
+	UnmatchedResponse(Output),
+	/// Id is already in use by pending call or
+	/// has been answered more than once in a single batch.
+	DuplicatedId(Id)
+}
+
+/// Hands out request ids and matches responses with callers.
+///
+/// ```rust
+/// extern crate jsonrpc_core;
+/// use jsonrpc_core::*;
+/// use jsonrpc_core::client::*;
+///
+/// fn main() {
+/// 	let mut manager = RequestManager::new();
+/// 	let call = manager.method_call("say_hello", Params::None, "caller");
+/// 	assert_eq!(call.id, Id::Num(0));
+///
+/// 	let response = read_response(r#"{"jsonrpc":"2.0","result":"hello","id":0}"#).unwrap();
+/// 	let results = manager.handle_response(response);
+/// 	assert_eq!(results, vec![Ok(("caller", Ok(Value::String("hello".to_owned()))))]);
+/// }
+/// ```
+pub struct RequestManager<T> {
+	next_id: u64,
+	pending: HashMap<Id, T>
+}
+
+impl<T> RequestManager<T> {
+	pub fn new() -> Self {
+		RequestManager {
+			next_id: 0,
+			pending: HashMap::new()
+		}
+	}
+
+	/// Returns next unused id. Ids added with `add_pending` are skipped.
+	pub fn next_id(&mut self) -> Id {
+		loop {
+			let id = Id::Num(self.next_id);
+			self.next_id += 1;
+			if !self.pending.contains_key(&id) {
+				return id;
+			}
+		}
+	}
+
+	/// Creates new method call and marks it as pending.
+	pub fn method_call(&mut self, method: &str, params: Params, caller: T) -> MethodCall {
+		let id = self.next_id();
+		self.pending.insert(id.clone(), caller);
+		MethodCall::new(method, params, id)
+	}
+
+	/// Marks call with given id as pending.
+	/// Should be used for calls with ids not created by this manager.
+	pub fn add_pending(&mut self, id: Id, caller: T) -> Result<(), MatchError> {
+		if self.pending.contains_key(&id) {
+			return Err(MatchError::DuplicatedId(id));
+		}
+		self.pending.insert(id, caller);
+		Ok(())
+	}
+
+	/// Returns number of calls which are still waiting for response.
+	pub fn pending(&self) -> usize {
+		self.pending.len()
+	}
+
+	/// Matches single output with its caller.
+	///
+	/// Failure with `null` id, sent when server can't read the id of the call,
+	/// doesn't tell which call it answers, so it's unmatched.
+	/// See `handle_call_response`.
+	pub fn handle_output(&mut self, output: Output) -> Result<(T, Result<Value, Error>), MatchError> {
+		match self.pending.remove(output.id()) {
+			Some(caller) => Ok((caller, output.into_result())),
+			None => Err(MatchError::UnmatchedResponse(output))
+		}
+	}
+
+	/// Matches every output of the response with its caller.
+	/// Batch outputs may come in any order.
+	pub fn handle_response(&mut self, response: Response) -> Vec<Result<(T, Result<Value, Error>), MatchError>> {
+		match response {
+			Response::Single(output) => vec![self.handle_output(output)],
+			Response::Batch(outputs) => {
+				let mut answered = HashSet::new();
+				outputs.into_iter().map(|output| {
+					let id = output.id().clone();
+					if answered.contains(&id) {
+						return Err(MatchError::DuplicatedId(id));
+					}
+					let result = self.handle_output(output);
+					if result.is_ok() {
+						answered.insert(id);
+					}
+					result
+				}).collect()
+			}
+		}
+	}
+
+	/// Matches response to a request made of the single call with given id.
+	///
+	/// Single failure with `null` id can only answer that call, so it's matched with it.
+	/// Other responses are matched as by `handle_response`.
+	pub fn handle_call_response(&mut self, id: &Id, response: Response) -> Vec<Result<(T, Result<Value, Error>), MatchError>> {
+		let response = match response {
+			Response::Single(Output::Failure(Failure { id: Id::Null, jsonrpc, error })) => Response::Single(Output::Failure(Failure {
+				id: id.clone(),
+				jsonrpc: jsonrpc,
+				error: error
+			})),
+			response => response
+		};
+		self.handle_response(response)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::super::*;
+
+	fn success(id: u64, result: u64) -> Output {
+		Output::Success(Success {
+			jsonrpc: Version::V2,
+			result: Value::U64(result),
+			id: Id::Num(id)
+		})
+	}
+
+	#[test]
+	fn test_batch_out_of_order() {
+		let mut manager = RequestManager::new();
+		manager.method_call("a", Params::None, 'a');
+		manager.method_call("b", Params::None, 'b');
+
+		let results = manager.handle_response(Response::Batch(vec![success(1, 20), success(0, 10)]));
+		assert_eq!(results, vec![Ok(('b', Ok(Value::U64(20)))), Ok(('a', Ok(Value::U64(10))))]);
+		assert_eq!(manager.pending(), 0);
+	}
+
+	#[test]
+	fn test_unmatched_and_duplicated() {
+		let mut manager = RequestManager::new();
+		manager.method_call("a", Params::None, 'a');
+
+		let results = manager.handle_response(Response::Batch(vec![success(0, 10), success(0, 10), success(5, 10)]));
+		assert_eq!(results, vec![
+			Ok(('a', Ok(Value::U64(10)))),
+			Err(MatchError::DuplicatedId(Id::Num(0))),
+			Err(MatchError::UnmatchedResponse(success(5, 10)))
+		]);
+
+		manager.add_pending(Id::Str("x".to_owned()), 'x').unwrap();
+		assert_eq!(manager.add_pending(Id::Str("x".to_owned()), 'y'), Err(MatchError::DuplicatedId(Id::Str("x".to_owned()))));
+	}
+
+	#[test]
+	fn test_skip_pending_ids() {
+		let mut manager = RequestManager::new();
+		manager.add_pending(Id::Num(0), 'x').unwrap();
+		assert_eq!(manager.method_call("a", Params::None, 'a').id, Id::Num(1));
+		assert_eq!(manager.pending(), 2);
+	}
+
+	#[test]
+	fn test_null_id_failure() {
+		let failure = || Output::Failure(Failure {
+			jsonrpc: Version::V2,
+			error: Error::parse_error(),
+			id: Id::Null
+		});
+
+		let mut manager = RequestManager::new();
+		manager.method_call("a", Params::None, 'a');
+		let b = manager.method_call("b", Params::None, 'b').id;
+		assert_eq!(manager.handle_output(failure()), Err(MatchError::UnmatchedResponse(failure())));
+
+		// it's unmatched regardless of how many calls are pending
+		manager.handle_output(success(0, 10)).unwrap();
+		assert_eq!(manager.handle_output(failure()), Err(MatchError::UnmatchedResponse(failure())));
+		assert_eq!(manager.handle_call_response(&b, Response::Batch(vec![failure()])), vec![Err(MatchError::UnmatchedResponse(failure()))]);
+
+		// caller which sent a single call knows what it answers
+		assert_eq!(manager.handle_call_response(&b, Response::Single(failure())), vec![Ok(('b', Err(Error::parse_error())))]);
+		assert_eq!(manager.pending(), 0);
+	}
+}
@@ -1,72 +1,280 @@
 //! method and notification commands executor
 
 use std::collections::HashMap;
-use super::{Params, Value, Error, ErrorCode};
+use std::marker::PhantomData;
+use std::sync::{Arc, mpsc};
+use serde::{Serialize, Deserialize};
+use serde_json::value::to_value;
+use super::{Params, Value, Error, ErrorCode, Metadata};
+use super::discover::{self, ServiceInfo, MethodInfo, DISCOVER_METHOD};
 
 /// Should be used to handle single method call.
 pub trait MethodCommand: Send + Sync {
-	fn execute(&mut self, params: Params) -> Result<Value, Error>;
+	fn execute(&self, params: Params) -> Result<Value, Error>;
 }
 
 /// Default method command implementation for closure.
 impl<F> MethodCommand for F where F: Fn(Params) -> Result<Value, Error>, F: Sync + Send {
-	fn execute(&mut self, params: Params) -> Result<Value, Error> {
+	fn execute(&self, params: Params) -> Result<Value, Error> {
 		self(params)
 	}
 }
 
+/// Completion handle of asynchronous method call.
+///
+/// If it's dropped without being called, method call fails with internal error.
+pub struct Ready {
+	callback: Option<Box<Fn(Result<Value, Error>) + Send>>
+}
+
+impl Ready {
+	pub fn new<F>(callback: F) -> Self where F: Fn(Result<Value, Error>) + Send + 'static {
+		Ready {
+			callback: Some(Box::new(callback))
+		}
+	}
+
+	/// Completes method call with given result.
+	pub fn ready(mut self, result: Result<Value, Error>) {
+		if let Some(callback) = self.callback.take() {
+			callback(result)
+		}
+	}
+}
+
+impl Drop for Ready {
+	fn drop(&mut self) {
+		if let Some(callback) = self.callback.take() {
+			callback(Err(Error::internal_error()))
+		}
+	}
+}
+
+/// Should be used to handle single method call asynchronously.
+pub trait AsyncMethodCommand: Send + Sync {
+	fn execute(&self, params: Params, ready: Ready);
+}
+
+/// Default async method command implementation for closure.
+impl<F> AsyncMethodCommand for F where F: Fn(Params, Ready), F: Sync + Send {
+	fn execute(&self, params: Params, ready: Ready) {
+		self(params, ready)
+	}
+}
+
+/// Method command with typed params and result.
+///
+/// Params are deserialized into closure argument,
+/// result is serialized into `Success.result`.
+pub struct TypedMethod<F, A, R, E> {
+	closure: F,
+	_marker: PhantomData<fn(A) -> Result<R, E>>
+}
+
+impl<F, A, R, E> TypedMethod<F, A, R, E> where F: Fn(A) -> Result<R, E> {
+	pub fn new(closure: F) -> Self {
+		TypedMethod {
+			closure: closure,
+			_marker: PhantomData
+		}
+	}
+}
+
+impl<F, A, R, E> MethodCommand for TypedMethod<F, A, R, E> where
+	F: Fn(A) -> Result<R, E>,
+	F: Send + Sync,
+	A: Deserialize,
+	R: Serialize,
+	E: Into<Error> {
+	fn execute(&self, params: Params) -> Result<Value, Error> {
+		let args = try!(params.parse::<A>());
+		let closure = &self.closure;
+		closure(args).map(|result| to_value(&result)).map_err(Into::into)
+	}
+}
+
 /// Should be used to handle single notification.
 pub trait NotificationCommand: Send + Sync {
-	fn execute(&mut self, params: Params);
+	fn execute(&self, params: Params);
 }
 
 /// Default notification command implementation for closure.
 impl<F> NotificationCommand for F where F: Fn(Params), F: Sync + Send {
-	fn execute(&mut self, params: Params) {
+	fn execute(&self, params: Params) {
 		self(params)
 	}
 }
 
+/// Should be used to handle single method call with request metadata.
+pub trait MetaMethodCommand<M>: Send + Sync where M: Metadata {
+	fn execute(&self, params: Params, meta: M) -> Result<Value, Error>;
+}
+
+/// Default meta method command implementation for closure.
+impl<M, F> MetaMethodCommand<M> for F where F: Fn(Params, M) -> Result<Value, Error>, F: Sync + Send, M: Metadata {
+	fn execute(&self, params: Params, meta: M) -> Result<Value, Error> {
+		self(params, meta)
+	}
+}
+
+/// Should be used to handle single method call asynchronously with request metadata.
+pub trait MetaAsyncMethodCommand<M>: Send + Sync where M: Metadata {
+	fn execute(&self, params: Params, meta: M, ready: Ready);
+}
+
+/// Default meta async method command implementation for closure.
+impl<M, F> MetaAsyncMethodCommand<M> for F where F: Fn(Params, M, Ready), F: Sync + Send, M: Metadata {
+	fn execute(&self, params: Params, meta: M, ready: Ready) {
+		self(params, meta, ready)
+	}
+}
+
+/// Should be used to handle single notification with request metadata.
+pub trait MetaNotificationCommand<M>: Send + Sync where M: Metadata {
+	fn execute(&self, params: Params, meta: M);
+}
+
+/// Default meta notification command implementation for closure.
+impl<M, F> MetaNotificationCommand<M> for F where F: Fn(Params, M), F: Sync + Send, M: Metadata {
+	fn execute(&self, params: Params, meta: M) {
+		self(params, meta)
+	}
+}
+
+/// Adapts commands which don't need request metadata.
+struct IgnoreMeta<C: ?Sized>(Box<C>);
+
+impl<M, C> MetaMethodCommand<M> for IgnoreMeta<C> where C: MethodCommand + ?Sized, M: Metadata {
+	fn execute(&self, params: Params, _meta: M) -> Result<Value, Error> {
+		self.0.execute(params)
+	}
+}
+
+impl<M, C> MetaAsyncMethodCommand<M> for IgnoreMeta<C> where C: AsyncMethodCommand + ?Sized, M: Metadata {
+	fn execute(&self, params: Params, _meta: M, ready: Ready) {
+		self.0.execute(params, ready)
+	}
+}
+
+impl<M, C> MetaNotificationCommand<M> for IgnoreMeta<C> where C: NotificationCommand + ?Sized, M: Metadata {
+	fn execute(&self, params: Params, _meta: M) {
+		self.0.execute(params)
+	}
+}
+
+/// Method command which can be executed outside of `Commander`.
+pub type SharedMethodCommand<M> = Arc<Box<MetaMethodCommand<M>>>;
+
 /// Commands executor.
-pub struct Commander {
-	methods: HashMap<String, Box<MethodCommand>>,
-	notifications: HashMap<String, Box<NotificationCommand>>
+///
+/// Once every command is added, it can be shared between threads
+/// and execute calls in parallel.
+pub struct Commander<M: Metadata = ()> {
+	methods: HashMap<String, SharedMethodCommand<M>>,
+	async_methods: HashMap<String, Box<MetaAsyncMethodCommand<M>>>,
+	notifications: HashMap<String, Box<MetaNotificationCommand<M>>>,
+	service: ServiceInfo,
+	descriptions: HashMap<String, MethodInfo>
 }
 
-impl Commander {
+impl<M> Commander<M> where M: Metadata {
 	pub fn new() -> Self {
 		Commander {
 			methods: HashMap::new(),
-			notifications: HashMap::new()
+			async_methods: HashMap::new(),
+			notifications: HashMap::new(),
+			service: ServiceInfo::default(),
+			descriptions: HashMap::new()
 		}
 	}
 
 	pub fn add_method<C>(&mut self, name: String, command: Box<C>) where C: MethodCommand + 'static {
-		self.methods.insert(name, command);
+		self.add_meta_method(name, Box::new(IgnoreMeta(command)))
+	}
+
+	pub fn add_meta_method<C>(&mut self, name: String, command: Box<C>) where C: MetaMethodCommand<M> + 'static {
+		self.methods.insert(name, Arc::new(command as Box<MetaMethodCommand<M>>));
 	}
 
 	pub fn add_methods(&mut self, methods: HashMap<String, Box<MethodCommand>>) {
-		self.methods.extend(methods);
+		for (name, command) in methods {
+			self.add_meta_method(name, Box::new(IgnoreMeta(command)));
+		}
+	}
+
+	pub fn add_async_method<C>(&mut self, name: String, command: Box<C>) where C: AsyncMethodCommand + 'static {
+		self.add_meta_async_method(name, Box::new(IgnoreMeta(command)))
+	}
+
+	pub fn add_meta_async_method<C>(&mut self, name: String, command: Box<C>) where C: MetaAsyncMethodCommand<M> + 'static {
+		self.async_methods.insert(name, command);
 	}
 
 	pub fn add_notification<C>(&mut self, name: String, command: Box<C>) where C: NotificationCommand + 'static {
+		self.add_meta_notification(name, Box::new(IgnoreMeta(command)))
+	}
+
+	pub fn add_meta_notification<C>(&mut self, name: String, command: Box<C>) where C: MetaNotificationCommand<M> + 'static {
 		self.notifications.insert(name, command);
 	}
 
 	pub fn add_notifications(&mut self, notifications: HashMap<String, Box<NotificationCommand>>) {
-		self.notifications.extend(notifications);
+		for (name, command) in notifications {
+			self.add_meta_notification(name, Box::new(IgnoreMeta(command)));
+		}
 	}
 
-	pub fn execute_method(&mut self, name: String, params: Params) -> Result<Value, Error> {
-		match self.methods.get_mut(&name) {
-			Some(command) => command.execute(params),
+	pub fn set_service_info(&mut self, service: ServiceInfo) {
+		self.service = service;
+	}
+
+	/// Describes method in discovery document.
+	pub fn describe_method(&mut self, name: String, info: MethodInfo) {
+		self.descriptions.insert(name, info);
+	}
+
+	/// Returns OpenRPC document of every method.
+	pub fn discover(&self) -> Value {
+		let names = self.methods.keys().chain(self.async_methods.keys());
+		discover::document(&self.service, names.map(|name| (name as &str, self.descriptions.get(name))))
+	}
+
+	/// Executes method. Blocks until asynchronous method is ready.
+	pub fn execute_method(&self, name: String, params: Params, meta: M) -> Result<Value, Error> {
+		if let Some(command) = self.methods.get(&name) {
+			return command.execute(params, meta);
+		}
+
+		match self.async_methods.get(&name) {
+			Some(command) => {
+				let (tx, rx) = mpsc::channel();
+				command.execute(params, meta, Ready::new(move |result| {
+					let _ = tx.send(result);
+				}));
+				rx.recv().unwrap_or_else(|_| Err(Error::internal_error()))
+			},
+			None if name == DISCOVER_METHOD => Ok(self.discover()),
 			None => Err(Error::new(ErrorCode::MethodNotFound))
 		}
 	}
 
-	pub fn execute_notification(&mut self, name: String, params: Params) {
-		if let Some(command) = self.notifications.get_mut(&name) {
-			command.execute(params)
+	/// Returns synchronous method command, which can be executed on other thread.
+	pub fn shared_method(&self, name: &str) -> Option<SharedMethodCommand<M>> {
+		self.methods.get(name).cloned()
+	}
+
+	/// Executes method and passes its result to `ready`.
+	pub fn execute_method_async(&self, name: String, params: Params, meta: M, ready: Ready) {
+		if let Some(command) = self.async_methods.get(&name) {
+			return command.execute(params, meta, ready);
+		}
+
+		ready.ready(self.execute_method(name, params, meta))
+	}
+
+	pub fn execute_notification(&self, name: String, params: Params, meta: M) {
+		if let Some(command) = self.notifications.get(&name) {
+			command.execute(params, meta)
 		}
 	}
 }
@@ -0,0 +1,136 @@
+//! service discovery with OpenRPC documents
+//!
+//! Every io handler answers `rpc.discover` with an OpenRPC document
+//! listing its methods, unless a method with that name is added.
+use std::collections::BTreeMap;
+use super::Value;
+
+/// Name of the built-in discovery method.
+pub const DISCOVER_METHOD: &'static str = "rpc.discover";
+const OPENRPC_VERSION: &'static str = "1.2.6";
+
+/// Title and version of the service.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceInfo {
+	pub title: String,
+	pub version: String
+}
+
+impl ServiceInfo {
+	pub fn new(title: &str, version: &str) -> Self {
+		ServiceInfo {
+			title: title.to_owned(),
+			version: version.to_owned()
+		}
+	}
+}
+
+impl Default for ServiceInfo {
+	fn default() -> Self {
+		ServiceInfo::new("jsonrpc", "0.0.0")
+	}
+}
+
+/// Named param or result with its JSON schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamInfo {
+	pub name: String,
+	pub schema: Value
+}
+
+impl ParamInfo {
+	/// Creates param with schema of given JSON type, e.g. `"integer"`.
+	pub fn new(name: &str, json_type: &str) -> Self {
+		let mut schema = BTreeMap::new();
+		schema.insert("type".to_owned(), Value::String(json_type.to_owned()));
+		ParamInfo {
+			name: name.to_owned(),
+			schema: Value::Object(schema)
+		}
+	}
+
+	fn to_value(&self) -> Value {
+		let mut object = BTreeMap::new();
+		object.insert("name".to_owned(), Value::String(self.name.clone()));
+		object.insert("schema".to_owned(), self.schema.clone());
+		Value::Object(object)
+	}
+}
+
+/// Optional description of a method.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MethodInfo {
+	pub description: Option<String>,
+	pub params: Vec<ParamInfo>,
+	pub result: Option<ParamInfo>,
+	pub deprecated: bool
+}
+
+/// Creates OpenRPC document of given methods.
+/// Methods are listed in the order of their names.
+pub fn document<'a, I>(service: &ServiceInfo, methods: I) -> Value where I: Iterator<Item = (&'a str, Option<&'a MethodInfo>)> {
+	let default_info = MethodInfo::default();
+	let mut methods: Vec<_> = methods.collect();
+	methods.sort_by(|a, b| a.0.cmp(b.0));
+
+	let methods = methods.into_iter().map(|(name, info)| {
+		let info = info.unwrap_or(&default_info);
+		let mut method = BTreeMap::new();
+		method.insert("name".to_owned(), Value::String(name.to_owned()));
+		method.insert("params".to_owned(), Value::Array(info.params.iter().map(ParamInfo::to_value).collect()));
+		let result = info.result.clone().unwrap_or_else(|| ParamInfo {
+			name: "result".to_owned(),
+			schema: Value::Object(BTreeMap::new())
+		});
+		method.insert("result".to_owned(), result.to_value());
+		if let Some(ref description) = info.description {
+			method.insert("description".to_owned(), Value::String(description.clone()));
+		}
+		if info.deprecated {
+			method.insert("deprecated".to_owned(), Value::Bool(true));
+		}
+		Value::Object(method)
+	}).collect();
+
+	let mut info = BTreeMap::new();
+	info.insert("title".to_owned(), Value::String(service.title.clone()));
+	info.insert("version".to_owned(), Value::String(service.version.clone()));
+
+	let mut document = BTreeMap::new();
+	document.insert("openrpc".to_owned(), Value::String(OPENRPC_VERSION.to_owned()));
+	document.insert("info".to_owned(), Value::Object(info));
+	document.insert("methods".to_owned(), Value::Array(methods));
+	Value::Object(document)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::*;
+
+	#[test]
+	fn test_rpc_discover() {
+		let mut io = IoHandler::new();
+		io.add_typed_method("add", |(a, b): (u64, u64)| -> Result<u64, Error> { Ok(a + b) });
+		io.add_method("ping", |_params: Params| -> Result<Value, Error> { Ok(Value::Null) });
+		io.set_service_info(ServiceInfo::new("calc", "1.0.0"));
+		io.describe_method("add", MethodInfo {
+			description: Some("Adds two numbers.".to_owned()),
+			params: vec![ParamInfo::new("a", "integer"), ParamInfo::new("b", "integer")],
+			result: Some(ParamInfo::new("sum", "integer")),
+			deprecated: true
+		});
+
+		let request = r#"{"jsonrpc": "2.0", "method": "rpc.discover", "id": 1}"#;
+		let response = concat!(
+			r#"{"jsonrpc":"2.0","result":{"info":{"title":"calc","version":"1.0.0"},"methods":["#,
+			r#"{"deprecated":true,"description":"Adds two numbers.","name":"add","#,
+			r#""params":[{"name":"a","schema":{"type":"integer"}},{"name":"b","schema":{"type":"integer"}}],"#,
+			r#""result":{"name":"sum","schema":{"type":"integer"}}},"#,
+			r#"{"name":"ping","params":[],"result":{"name":"result","schema":{}}}"#,
+			r#"],"openrpc":"1.2.6"},"id":1}"#
+		);
+
+		assert_eq!(io.handle_request(request), Some(response.to_owned()));
+	}
+}
@@ -1,5 +1,6 @@
 //! jsonrpc errors
-use serde::{Serialize, Serializer};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Visitor;
 use super::Value;
 
 #[derive(Debug, PartialEq)]
@@ -44,6 +45,19 @@ impl ErrorCode {
 	}
 }
 
+impl From<i64> for ErrorCode {
+	fn from(code: i64) -> Self {
+		match code {
+			-32700 => ErrorCode::ParseError,
+			-32600 => ErrorCode::InvalidRequest,
+			-32601 => ErrorCode::MethodNotFound,
+			-32602 => ErrorCode::InvalidParams,
+			-32603 => ErrorCode::InternalError,
+			code => ErrorCode::ServerError(code)
+		}
+	}
+}
+
 impl Serialize for ErrorCode {
 	fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error> 
 	where S: Serializer {
@@ -51,7 +65,31 @@ impl Serialize for ErrorCode {
 	}
 }
 
-#[derive(Debug, PartialEq, Serialize)]
+impl Deserialize for ErrorCode {
+	fn deserialize<D>(deserializer: &mut D) -> Result<ErrorCode, D::Error>
+	where D: Deserializer {
+		deserializer.visit(ErrorCodeVisitor)
+	}
+}
+
+struct ErrorCodeVisitor;
+
+impl Visitor for ErrorCodeVisitor {
+	type Value = ErrorCode;
+
+	fn visit_i64<E>(&mut self, value: i64) -> Result<Self::Value, E> where E: ::serde::Error {
+		Ok(ErrorCode::from(value))
+	}
+
+	fn visit_u64<E>(&mut self, value: u64) -> Result<Self::Value, E> where E: ::serde::Error {
+		match value > i64::max_value() as u64 {
+			true => Err(::serde::Error::syntax("error code out of range")),
+			false => Ok(ErrorCode::from(value as i64))
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Error {
 	pub code: ErrorCode,
 	pub message: String,
@@ -87,3 +125,38 @@ impl Error {
 		Self::new(ErrorCode::InternalError)
 	}
 }
+
+#[cfg(test)]
+impl super::rng::Rng {
+	pub fn error(&mut self) -> Error {
+		let codes = [-32700, -32600, -32601, -32602, -32603, -32000];
+		let code = match self.below(2) {
+			0 => codes[self.below(codes.len() as u64) as usize],
+			_ => self.next() as i64
+		};
+		// `Some(Value::Null)` is serialized like `None`
+		let data = match self.value(2) {
+			Value::Null => None,
+			data => Some(data)
+		};
+		Error {
+			code: ErrorCode::from(code),
+			message: self.string(),
+			data: data
+		}
+	}
+}
+
+#[test]
+fn error_roundtrip() {
+	use serde_json;
+	use super::rng::{Rng, ITERATIONS, assert_roundtrip};
+
+	let mut rng = Rng(0x9e3779b97f4a7c15);
+	for _ in 0..ITERATIONS {
+		assert_roundtrip(rng.error());
+	}
+
+	let out_of_range: Result<Error, _> = serde_json::from_str(r#"{"code":9223372036854775808,"message":"","data":null}"#);
+	assert!(out_of_range.is_err());
+}
@@ -2,10 +2,16 @@
 use serde::{Serialize, Serializer, Deserialize, Deserializer, Error};
 use serde::de::Visitor;
 
-#[derive(Debug, PartialEq)]
+/// Request id.
+///
+/// Fractional numbers are rejected, because they can't be matched reliably.
+#[derive(Debug, PartialEq, Clone, Hash, Eq)]
 pub enum Id {
 	Null,
 	Num(u64),
+	/// Negative number.
+	NegNum(i64),
+	Str(String)
 }
 
 impl Serialize for Id {
@@ -13,7 +19,9 @@ impl Serialize for Id {
 	where S: Serializer {
 		match self {
 			&Id::Null => serializer.visit_unit(),
-			&Id::Num(v) => serializer.visit_u64(v)
+			&Id::Num(v) => serializer.visit_u64(v),
+			&Id::NegNum(v) => serializer.visit_i64(v),
+			&Id::Str(ref s) => serializer.visit_str(s)
 		}
 	}
 }
@@ -34,16 +42,27 @@ impl Visitor for IdVisitor {
 		Ok(Id::Null)
 	}
 
+	fn visit_i64<E>(&mut self, value: i64) -> Result<Self::Value, E> where E: Error {
+		match value < 0 {
+			true => Ok(Id::NegNum(value)),
+			false => Ok(Id::Num(value as u64))
+		}
+	}
+
 	fn visit_u64<E>(&mut self, value: u64) -> Result<Self::Value, E> where E: Error {
 		Ok(Id::Num(value))
 	}
 
+	fn visit_f64<E>(&mut self, _value: f64) -> Result<Self::Value, E> where E: Error {
+		Err(Error::syntax("fractional id"))
+	}
+
 	fn visit_str<E>(&mut self, value: &str) -> Result<Self::Value, E> where E: Error {
 		self.visit_string(value.to_string())
 	}
 
 	fn visit_string<E>(&mut self, value: String) -> Result<Self::Value, E> where E: Error {
-		value.parse::<u64>().map(Id::Num).map_err(|_| Error::syntax("invalid id"))
+		Ok(Id::Str(value))
 	}
 }
 
@@ -51,18 +70,48 @@ impl Visitor for IdVisitor {
 mod tests {
 	use super::*;
 	use serde_json;
+	use super::super::rng::{Rng, ITERATIONS, assert_roundtrip};
 
 	#[test]
 	fn id_deserialization() {
-		let s = r#"[null, 0, 2, "3"]"#;
+		let s = r#"[null, 0, 2, -5, 18446744073709551615, "3", "abc-123"]"#;
 		let deserialized: Vec<Id> = serde_json::from_str(s).unwrap();
-		assert_eq!(deserialized, vec![Id::Null, Id::Num(0), Id::Num(2), Id::Num(3)]);
+		assert_eq!(deserialized, vec![
+			Id::Null, Id::Num(0), Id::Num(2), Id::NegNum(-5), Id::Num(u64::max_value()),
+			Id::Str("3".to_owned()), Id::Str("abc-123".to_owned())
+		]);
 	}
 
 	#[test]
 	fn id_serialization() {
-		let d = vec![Id::Null, Id::Num(0), Id::Num(2), Id::Num(3)];
+		let d = vec![Id::Null, Id::Num(0), Id::Num(2), Id::NegNum(-5), Id::Str("3".to_owned())];
 		let serialized = serde_json::to_string(&d).unwrap();
-		assert_eq!(serialized, r#"[null,0,2,3]"#);
+		assert_eq!(serialized, r#"[null,0,2,-5,"3"]"#);
+	}
+
+	#[test]
+	fn id_fractional_deserialization() {
+		let deserialized: Result<Id, _> = serde_json::from_str("1.5");
+		assert!(deserialized.is_err());
+	}
+
+	impl Rng {
+		pub fn id(&mut self) -> Id {
+			match self.below(5) {
+				0 => Id::Null,
+				1 => Id::Num(self.next()),
+				2 => Id::Num(u64::max_value()),
+				3 => Id::NegNum(-(self.below(i64::max_value() as u64) as i64) - 1),
+				_ => Id::Str(self.string())
+			}
+		}
+	}
+
+	#[test]
+	fn id_roundtrip() {
+		let mut rng = Rng(0x2545f4914f6cdd1d);
+		for _ in 0..ITERATIONS {
+			assert_roundtrip(rng.id());
+		}
 	}
 }
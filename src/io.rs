@@ -1,6 +1,11 @@
 //! jsonrpc io
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::{HashMap, BTreeMap};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::ops::{Deref, DerefMut};
+use serde::{Serialize, Deserialize};
 use serde_json;
 use super::*;
 
@@ -16,7 +21,7 @@ impl<T, F> MethodCommand for DelegateMethod <T, F> where
 	F: Fn(&T, Params) -> Result<Value, Error>, 
 	F: Send + Sync,
 	T: Send + Sync {
-	fn execute(&mut self, params: Params) -> Result<Value, Error> {
+	fn execute(&self, params: Params) -> Result<Value, Error> {
 		let closure = &self.closure;
 		closure(&self.delegate, params)
 	}
@@ -34,7 +39,7 @@ impl<T, F> NotificationCommand for DelegateNotification<T, F> where
 	F: Fn(&T, Params),
 	F: Send + Sync,
 	T: Send + Sync {
-	fn execute(&mut self, params: Params) {
+	fn execute(&self, params: Params) {
 		let closure = &self.closure;
 		closure(&self.delegate, params)
 	}
@@ -73,6 +78,9 @@ impl<T> IoDelegate<T> where T: Send + Sync + 'static {
 }
 
 /// Should be used to handle jsonrpc io.
+///
+/// Requests are handled through `&self`, so once every method is added,
+/// io handler can be wrapped in `Arc` and shared between threads.
 /// 
 /// ```rust
 /// extern crate jsonrpc_core;
@@ -82,7 +90,7 @@ impl<T> IoDelegate<T> where T: Send + Sync + 'static {
 /// 	let mut io = IoHandler::new();
 /// 	struct SayHello;
 /// 	impl MethodCommand for SayHello {
-/// 		fn execute(&mut self, _params: Params) -> Result<Value, Error> {
+/// 		fn execute(&self, _params: Params) -> Result<Value, Error> {
 /// 			Ok(Value::String("hello".to_string()))
 /// 		}
 /// 	}
@@ -95,12 +103,99 @@ impl<T> IoDelegate<T> where T: Send + Sync + 'static {
 /// 	assert_eq!(io.handle_request(request), Some(response.to_string()));
 /// }
 /// ```
-pub struct IoHandler {
-	request_handler: RequestHandler
+pub struct IoHandler(MetaIoHandler<()>);
+
+impl IoHandler {
+	pub fn new() -> Self {
+		IoHandler(MetaIoHandler::new())
+	}
+
+	/// Creates io handler which executes batch calls concurrently
+	/// on given number of threads. `0` executes them on the calling thread.
+	pub fn with_threads(threads: usize) -> Self {
+		IoHandler(MetaIoHandler::with_threads(threads))
+	}
+}
+
+impl From<IoHandler> for MetaIoHandler<()> {
+	fn from(io: IoHandler) -> Self {
+		io.0
+	}
+}
+
+impl Deref for IoHandler {
+	type Target = MetaIoHandler<()>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for IoHandler {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+/// Should be used to handle jsonrpc io with request metadata.
+///
+/// ```rust
+/// extern crate jsonrpc_core;
+/// use jsonrpc_core::*;
+///
+/// #[derive(Default, Clone)]
+/// struct Session {
+/// 	user: String
+/// }
+///
+/// impl Metadata for Session {}
+///
+/// fn main() {
+/// 	let mut io = MetaIoHandler::new();
+/// 	io.add_meta_method("whoami", |_params: Params, session: Session| -> Result<Value, Error> {
+/// 		Ok(Value::String(session.user))
+/// 	});
+///
+/// 	let request = r#"{"jsonrpc": "2.0", "method": "whoami", "id": 1}"#;
+/// 	let response = r#"{"jsonrpc":"2.0","result":"alice","id":1}"#;
+///
+/// 	assert_eq!(io.handle_request_with_meta(request, Session { user: "alice".to_owned() }), Some(response.to_string()));
+/// }
+/// ```
+pub struct MetaIoHandler<M: Metadata> {
+	request_handler: RequestHandler<M>,
+	verbose_errors: bool
+}
+
+/// Number of characters around the error position included in its snippet.
+const SNIPPET_CONTEXT: usize = 20;
+
+fn read_request(request_str: &str, verbose_errors: bool) -> Result<Request, Error> {
+	serde_json::from_str(request_str).map_err(|e| match verbose_errors {
+		true => verbose_parse_error(request_str, &e),
+		false => Error::new(ErrorCode::ParseError)
+	})
 }
 
-fn read_request(request_str: &str) -> Result<Request, Error> {
-	serde_json::from_str(request_str).map_err(|_| Error::new(ErrorCode::ParseError))
+/// Creates `ParseError` with serde message, position and snippet
+/// of the offending input in `data`.
+fn verbose_parse_error(request_str: &str, e: &serde_json::Error) -> Error {
+	let mut data = BTreeMap::new();
+	data.insert("message".to_owned(), Value::String(format!("{}", e)));
+
+	if let serde_json::Error::SyntaxError(_, line, column) = *e {
+		data.insert("line".to_owned(), Value::U64(line as u64));
+		data.insert("column".to_owned(), Value::U64(column as u64));
+		if let Some(text) = request_str.lines().nth(line.saturating_sub(1)) {
+			let start = column.saturating_sub(SNIPPET_CONTEXT + 1);
+			let snippet: String = text.chars().skip(start).take(SNIPPET_CONTEXT * 2 + 1).collect();
+			data.insert("snippet".to_owned(), Value::String(snippet));
+		}
+	}
+
+	let mut error = Error::parse_error();
+	error.data = Some(Value::Object(data));
+	error
 }
 
 fn write_response(response: Response) -> String {
@@ -108,42 +203,193 @@ fn write_response(response: Response) -> String {
 	serde_json::to_string(&response).unwrap()
 }
 
-impl IoHandler {
+fn parse_error_response(error: Error) -> String {
+	write_response(Response::Single(Output::Failure(Failure {
+		id: Id::Null,
+		jsonrpc: Version::V2,
+		error: error
+	})))
+}
+
+impl<M> MetaIoHandler<M> where M: Metadata {
 	pub fn new() -> Self {
-		IoHandler {
-			request_handler: RequestHandler::new()
+		MetaIoHandler {
+			request_handler: RequestHandler::new(),
+			verbose_errors: false
+		}
+	}
+
+	/// Creates io handler which executes batch calls concurrently
+	/// on given number of threads. `0` executes them on the calling thread.
+	pub fn with_threads(threads: usize) -> Self {
+		MetaIoHandler {
+			request_handler: RequestHandler::with_threads(threads),
+			verbose_errors: false
 		}
 	}
 
+	/// Puts serde message, position and snippet of malformed requests
+	/// into `data` of parse errors. Disabled by default, so production
+	/// services don't echo client input back.
+	pub fn set_verbose_errors(&mut self, verbose_errors: bool) {
+		self.verbose_errors = verbose_errors;
+	}
+
 	#[inline]
 	pub fn add_method<C>(&mut self, name: &str, command: C) where C: MethodCommand + 'static {
 		self.request_handler.add_method(name.to_owned(), Box::new(command))
 	}
 
+	#[inline]
+	pub fn add_meta_method<C>(&mut self, name: &str, command: C) where C: MetaMethodCommand<M> + 'static {
+		self.request_handler.add_meta_method(name.to_owned(), Box::new(command))
+	}
+
+	/// Adds method with typed params and result.
+	///
+	/// ```rust
+	/// extern crate jsonrpc_core;
+	/// use jsonrpc_core::*;
+	///
+	/// fn main() {
+	/// 	let mut io = IoHandler::new();
+	/// 	io.add_typed_method("add", |(a, b): (u64, u64)| -> Result<u64, Error> { Ok(a + b) });
+	///
+	/// 	let request = r#"{"jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 1}"#;
+	/// 	let response = r#"{"jsonrpc":"2.0","result":3,"id":1}"#;
+	///
+	/// 	assert_eq!(io.handle_request(request), Some(response.to_string()));
+	/// }
+	/// ```
+	#[inline]
+	pub fn add_typed_method<F, A, R, E>(&mut self, name: &str, closure: F) where
+		F: Fn(A) -> Result<R, E> + Send + Sync + 'static,
+		A: Deserialize + 'static,
+		R: Serialize + 'static,
+		E: Into<Error> + 'static {
+		self.add_method(name, TypedMethod::new(closure))
+	}
+
+	#[inline]
+	pub fn add_async_method<C>(&mut self, name: &str, command: C) where C: AsyncMethodCommand + 'static {
+		self.request_handler.add_async_method(name.to_owned(), Box::new(command))
+	}
+
+	#[inline]
+	pub fn add_meta_async_method<C>(&mut self, name: &str, command: C) where C: MetaAsyncMethodCommand<M> + 'static {
+		self.request_handler.add_meta_async_method(name.to_owned(), Box::new(command))
+	}
+
 	#[inline]
 	pub fn add_notification<C>(&mut self, name: &str, command: C) where C: NotificationCommand + 'static {
 		self.request_handler.add_notification(name.to_owned(), Box::new(command))
 	}
 
+	#[inline]
+	pub fn add_meta_notification<C>(&mut self, name: &str, command: C) where C: MetaNotificationCommand<M> + 'static {
+		self.request_handler.add_meta_notification(name.to_owned(), Box::new(command))
+	}
+
+	#[inline]
+	pub fn add_middleware<W>(&mut self, middleware: W) where W: Middleware<M> + 'static {
+		self.request_handler.add_middleware(middleware)
+	}
+
 	pub fn add_delegate<D>(&mut self, delegate: IoDelegate<D>) where D: Send + Sync {
 		self.request_handler.add_methods(delegate.methods);
 		self.request_handler.add_notifications(delegate.notifications);
 	}
 
-	pub fn handle_request<'a>(&mut self, request_str: &'a str) -> Option<String> {
-		match read_request(request_str) {
-			Ok(request) => self.request_handler.handle_request(request).map(write_response),
-			Err(error) => Some(write_response(Response::Single(Output::Failure(Failure {
-				id: Id::Null,
-				jsonrpc: Version::V2,
-				error: error
-			}))))
+	/// Sets title and version of the service in discovery document.
+	#[inline]
+	pub fn set_service_info(&mut self, service: ServiceInfo) {
+		self.request_handler.set_service_info(service)
+	}
+
+	/// Describes method in discovery document returned by `rpc.discover`.
+	#[inline]
+	pub fn describe_method(&mut self, name: &str, info: MethodInfo) {
+		self.request_handler.describe_method(name.to_owned(), info)
+	}
+
+	/// Returns OpenRPC document of every method.
+	#[inline]
+	pub fn discover(&self) -> Value {
+		self.request_handler.discover()
+	}
+
+	/// Writes OpenRPC document of every method to the file at given path.
+	pub fn export_discover<P>(&self, path: P) -> ::std::io::Result<()> where P: AsRef<Path> {
+		let mut file = try!(File::create(path));
+		// this should never fail
+		try!(file.write_all(serde_json::to_string_pretty(&self.discover()).unwrap().as_bytes()));
+		file.write_all(b"\n")
+	}
+
+	/// Handles request with default metadata.
+	#[inline]
+	pub fn handle_request<'a>(&self, request_str: &'a str) -> Option<String> {
+		self.handle_request_with_meta(request_str, M::default())
+	}
+
+	pub fn handle_request_with_meta<'a>(&self, request_str: &'a str, meta: M) -> Option<String> {
+		match read_request(request_str, self.verbose_errors) {
+			Ok(request) => self.handle_rpc_request(request, meta).map(write_response),
+			Err(error) => Some(parse_error_response(error))
+		}
+	}
+
+	/// Handles request which is already parsed.
+	#[inline]
+	pub fn handle_rpc_request(&self, request: Request, meta: M) -> Option<Response> {
+		self.request_handler.handle_request(request, meta)
+	}
+
+	/// Handles request with default metadata without blocking on asynchronous methods.
+	/// `on_response` is called once every call of the request is done.
+	///
+	/// ```rust
+	/// extern crate jsonrpc_core;
+	/// use std::thread;
+	/// use std::sync::mpsc;
+	/// use jsonrpc_core::*;
+	///
+	/// fn main() {
+	/// 	let mut io = IoHandler::new();
+	/// 	io.add_async_method("say_hello", |_params: Params, ready: Ready| {
+	/// 		thread::spawn(move || ready.ready(Ok(Value::String("hello".to_string()))));
+	/// 	});
+	///
+	/// 	let (tx, rx) = mpsc::channel();
+	/// 	let request = r#"{"jsonrpc": "2.0", "method": "say_hello", "params": [42, 23], "id": 1}"#;
+	/// 	io.handle_request_async(request, move |response| tx.send(response).unwrap());
+	///
+	/// 	let response = r#"{"jsonrpc":"2.0","result":"hello","id":1}"#;
+	/// 	assert_eq!(rx.recv().unwrap(), Some(response.to_string()));
+	/// }
+	/// ```
+	#[inline]
+	pub fn handle_request_async<F>(&self, request_str: &str, on_response: F) where F: FnOnce(Option<String>) + Send + 'static {
+		self.handle_request_async_with_meta(request_str, M::default(), on_response)
+	}
+
+	pub fn handle_request_async_with_meta<F>(&self, request_str: &str, meta: M, on_response: F) where F: FnOnce(Option<String>) + Send + 'static {
+		match read_request(request_str, self.verbose_errors) {
+			Ok(request) => self.request_handler.handle_request_async(request, meta, move |response| on_response(response.map(write_response))),
+			Err(error) => on_response(Some(parse_error_response(error)))
 		}
 	}
+
+	/// Handles request which is already parsed without blocking on asynchronous methods.
+	#[inline]
+	pub fn handle_rpc_request_async<F>(&self, request: Request, meta: M, on_response: F) where F: FnOnce(Option<Response>) + Send + 'static {
+		self.request_handler.handle_request_async(request, meta, on_response)
+	}
 }
 
 #[cfg(test)]
 mod tests {
+	use serde_json;
 	use super::super::*;
 
 	#[test]
@@ -152,7 +398,7 @@ mod tests {
 		
 		struct SayHello;
 		impl MethodCommand for SayHello {
-			fn execute(&mut self, _params: Params) -> Result<Value, Error> {
+			fn execute(&self, _params: Params) -> Result<Value, Error> {
 				Ok(Value::String("hello".to_string()))
 			}
 		}
@@ -164,4 +410,165 @@ mod tests {
 
 		assert_eq!(io.handle_request(request), Some(response.to_string()));
 	}
+
+	#[test]
+	fn test_typed_method() {
+		let mut io = IoHandler::new();
+		io.add_typed_method("repeat", |(times, s): (u64, String)| -> Result<String, Error> {
+			Ok((0..times).map(|_| s.as_ref()).collect::<Vec<&str>>().join(""))
+		});
+		io.add_method("untyped", |_params: Params| -> Result<Value, Error> { Ok(Value::Null) });
+
+		let request = r#"{"jsonrpc": "2.0", "method": "repeat", "params": [2, "ab"], "id": 1}"#;
+		let response = r#"{"jsonrpc":"2.0","result":"abab","id":1}"#;
+		assert_eq!(io.handle_request(request), Some(response.to_string()));
+
+		let request = r#"{"jsonrpc": "2.0", "method": "repeat", "params": ["ab"], "id": 2}"#;
+		let response = io.handle_request(request).unwrap();
+		assert!(response.contains("-32602"));
+
+		let request = r#"{"jsonrpc": "2.0", "method": "untyped", "id": 3}"#;
+		let response = r#"{"jsonrpc":"2.0","result":null,"id":3}"#;
+		assert_eq!(io.handle_request(request), Some(response.to_string()));
+	}
+
+	#[test]
+	fn test_async_batch() {
+		use std::thread;
+		use std::time::Duration;
+		use std::sync::mpsc;
+
+		let mut io = IoHandler::new();
+		io.add_async_method("sleep", |params: Params, ready: Ready| {
+			thread::spawn(move || {
+				let (ms,): (u64,) = params.parse().unwrap();
+				thread::sleep(Duration::from_millis(ms));
+				ready.ready(Ok(Value::U64(ms)))
+			});
+		});
+		io.add_async_method("forget", |_params: Params, _ready: Ready| {});
+		io.add_method("now", |_params: Params| -> Result<Value, Error> { Ok(Value::U64(0)) });
+
+		let (tx, rx) = mpsc::channel();
+		let request = r#"[
+			{"jsonrpc": "2.0", "method": "sleep", "params": [50], "id": 1},
+			{"jsonrpc": "2.0", "method": "now", "id": 2},
+			{"jsonrpc": "2.0", "method": "sleep", "params": [10], "id": 3},
+			{"jsonrpc": "2.0", "method": "forget", "id": 4}
+		]"#;
+		io.handle_request_async(request, move |response| tx.send(response).unwrap());
+
+		let response = r#"[{"jsonrpc":"2.0","result":50,"id":1},{"jsonrpc":"2.0","result":0,"id":2},{"jsonrpc":"2.0","result":10,"id":3},{"jsonrpc":"2.0","error":{"code":-32603,"message":"Internal error.","data":null},"id":4}]"#;
+		assert_eq!(rx.recv().unwrap(), Some(response.to_string()));
+
+		// async methods can be called synchronously too
+		let request = r#"{"jsonrpc": "2.0", "method": "sleep", "params": [1], "id": 5}"#;
+		let response = r#"{"jsonrpc":"2.0","result":1,"id":5}"#;
+		assert_eq!(io.handle_request(request), Some(response.to_string()));
+	}
+
+	#[test]
+	fn test_concurrent_batch() {
+		use std::thread;
+		use std::time::{Duration, Instant};
+
+		let mut io = IoHandler::with_threads(3);
+		for &(name, ms) in &[("a", 300), ("b", 200), ("c", 100)] {
+			io.add_method(name, move |_params: Params| -> Result<Value, Error> {
+				thread::sleep(Duration::from_millis(ms));
+				Ok(Value::U64(ms))
+			});
+		}
+
+		let request = r#"[
+			{"jsonrpc": "2.0", "method": "a", "id": 1},
+			{"jsonrpc": "2.0", "method": "b", "id": 2},
+			{"jsonrpc": "2.0", "method": "c", "id": 3}
+		]"#;
+		let response = r#"[{"jsonrpc":"2.0","result":300,"id":1},{"jsonrpc":"2.0","result":200,"id":2},{"jsonrpc":"2.0","result":100,"id":3}]"#;
+
+		let start = Instant::now();
+		assert_eq!(io.handle_request(request), Some(response.to_string()));
+		assert!(start.elapsed() < Duration::from_millis(600));
+	}
+
+	#[test]
+	fn test_panicking_batch_call() {
+		let mut io = IoHandler::with_threads(1);
+		io.add_method("panic", |_params: Params| -> Result<Value, Error> {
+			panic!("method failed")
+		});
+		io.add_method("hello", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("hello".to_owned()))
+		});
+
+		let request = r#"[
+			{"jsonrpc": "2.0", "method": "panic", "id": 1},
+			{"jsonrpc": "2.0", "method": "hello", "id": 2}
+		]"#;
+		let response = r#"[{"jsonrpc":"2.0","error":{"code":-32603,"message":"Internal error.","data":null},"id":1},{"jsonrpc":"2.0","result":"hello","id":2}]"#;
+		assert_eq!(io.handle_request(request), Some(response.to_owned()));
+		// the only worker survived
+		assert_eq!(io.handle_request(request), Some(response.to_owned()));
+
+		// no threads, calls are executed as with `new`
+		let mut io = IoHandler::with_threads(0);
+		io.add_method("hello", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("hello".to_owned()))
+		});
+		let request = r#"[{"jsonrpc": "2.0", "method": "hello", "id": 2}]"#;
+		assert_eq!(io.handle_request(request), Some(r#"[{"jsonrpc":"2.0","result":"hello","id":2}]"#.to_owned()));
+	}
+
+	#[test]
+	fn test_shared_io_handler() {
+		use std::thread;
+		use std::sync::Arc;
+
+		let mut io = IoHandler::new();
+		io.add_typed_method("double", |(a,): (u64,)| -> Result<u64, Error> { Ok(a * 2) });
+		let io = Arc::new(io);
+
+		let handles: Vec<_> = (0..4).map(|i| {
+			let io = io.clone();
+			thread::spawn(move || {
+				let request = format!(r#"{{"jsonrpc": "2.0", "method": "double", "params": [{}], "id": {}}}"#, i, i);
+				io.handle_request(&request)
+			})
+		}).collect();
+
+		for (i, handle) in handles.into_iter().enumerate() {
+			let response = format!(r#"{{"jsonrpc":"2.0","result":{},"id":{}}}"#, i * 2, i);
+			assert_eq!(handle.join().unwrap(), Some(response));
+		}
+	}
+
+	#[test]
+	fn test_invalid_request_id() {
+		let io = IoHandler::new();
+
+		let request = r#"[{"jsonrpc": "2.0", "method": 1, "id": "a"}, {"jsonrpc": "1.0", "method": "x", "id": 2}, 3]"#;
+		let response = concat!(
+			r#"[{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request.","data":"`method` must be a string."},"id":"a"},"#,
+			r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request.","data":"`jsonrpc` must be exactly \"2.0\"."},"id":2},"#,
+			r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request.","data":"Request must be an object."},"id":null}]"#
+		);
+
+		assert_eq!(io.handle_request(request), Some(response.to_owned()));
+	}
+
+	#[test]
+	fn test_verbose_parse_error() {
+		let mut io = IoHandler::new();
+		let request = "{\n\"id\": ,\n}";
+		assert_eq!(io.handle_request(request), Some(r#"{"jsonrpc":"2.0","error":{"code":-32700,"message":"Parse error.","data":null},"id":null}"#.to_owned()));
+
+		io.set_verbose_errors(true);
+		let response: Value = serde_json::from_str(&io.handle_request(request).unwrap()).unwrap();
+		let data = response.lookup("error.data").unwrap();
+		assert_eq!(data.find("line"), Some(&Value::U64(2)));
+		assert_eq!(data.find("snippet"), Some(&Value::String("\"id\": ,".to_owned())));
+		assert!(data.find("column").unwrap().is_u64());
+		assert!(data.find("message").unwrap().is_string());
+	}
 }
@@ -1,6 +1,7 @@
 //! ### Transport agnostic jsonrpc library.
 //! 
-//! Right now it supports only server side handling requests.
+//! Supports server side handling requests and client side
+//! serialization of requests and responses.
 //! 
 //! ```rust
 //! extern crate jsonrpc_core;
@@ -9,7 +10,7 @@
 //!
 //! struct SayHello;
 //! impl MethodCommand for SayHello {
-//!     fn execute(&mut self, _params: Params) -> Result<Value, Error> {
+//!     fn execute(&self, _params: Params) -> Result<Value, Error> {
 //!         Ok(Value::String("hello".to_string()))
 //!     }
 //! }
@@ -30,16 +31,26 @@
 extern crate serde;
 extern crate serde_json;
 
+#[macro_use]
+pub mod macros;
 pub mod version;
 pub mod id;
 pub mod params;
 pub mod request;
 pub mod response;
 pub mod error;
+pub mod metadata;
+pub mod discover;
 pub mod commander;
+pub mod middleware;
 pub mod request_handler;
 pub mod io;
-mod peek;
+pub mod pubsub;
+pub mod transports;
+pub mod client;
+mod pool;
+#[cfg(test)]
+mod rng;
 
 pub use serde_json::Value;
 
@@ -49,6 +60,10 @@ pub use self::params::Params;
 pub use self::request::{Request, Call, MethodCall, Notification};
 pub use self::response::{Response, Output, Success, Failure};
 pub use self::error::{ErrorCode, Error};
-pub use self::commander::{Commander, MethodCommand, NotificationCommand};
+pub use self::metadata::Metadata;
+pub use self::discover::{ServiceInfo, MethodInfo, ParamInfo};
+pub use self::commander::{Commander, MethodCommand, AsyncMethodCommand, NotificationCommand, TypedMethod, Ready, SharedMethodCommand};
+pub use self::commander::{MetaMethodCommand, MetaAsyncMethodCommand, MetaNotificationCommand};
+pub use self::middleware::Middleware;
 pub use self::request_handler::RequestHandler;
-pub use self::io::{IoHandler, IoDelegate};
+pub use self::io::{IoHandler, MetaIoHandler, IoDelegate};
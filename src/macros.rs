@@ -0,0 +1,148 @@
+//! rpc trait definition macros
+
+#[doc(hidden)]
+pub use serde_json::value::to_value;
+
+/// Builds rpc trait with server side `IoDelegate` registration
+/// and client side stub sharing the same method names and types.
+///
+/// Every method has to be annotated with `#[rpc(name = "...")]`,
+/// params are passed as positional array.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate jsonrpc_core;
+/// use jsonrpc_core::*;
+/// use jsonrpc_core::client::RequestManager;
+///
+/// build_rpc_trait! {
+/// 	pub trait Calc {
+/// 		#[rpc(name = "calc_add")]
+/// 		fn add(&self, a: u64, b: u64) -> Result<u64, Error>;
+/// 	}
+///
+/// 	pub client CalcClient;
+/// }
+///
+/// struct CalcImpl;
+/// impl Calc for CalcImpl {
+/// 	fn add(&self, a: u64, b: u64) -> Result<u64, Error> {
+/// 		Ok(a + b)
+/// 	}
+/// }
+///
+/// fn main() {
+/// 	let mut io = IoHandler::new();
+/// 	io.add_delegate(CalcImpl.to_delegate());
+///
+/// 	let mut manager = RequestManager::new();
+/// 	let call = CalcClient::add(&mut manager, (), 1, 2);
+/// 	let request = client::write_request(&Request::Single(Call::MethodCall(call)));
+///
+/// 	assert_eq!(io.handle_request(&request), Some(r#"{"jsonrpc":"2.0","result":3,"id":0}"#.to_string()));
+/// }
+/// ```
+#[macro_export]
+macro_rules! build_rpc_trait {
+	(
+		$(#[$t_attr:meta])*
+		pub trait $name:ident {
+			$(
+				#[rpc(name = $rpc_name:expr)]
+				$(#[$m_attr:meta])*
+				fn $method:ident(&self $(, $arg:ident : $ty:ty)*) -> $out:ty;
+			)*
+		}
+
+		pub client $client:ident;
+	) => {
+		$(#[$t_attr])*
+		pub trait $name: Sized + Send + Sync + 'static {
+			$(
+				$(#[$m_attr])*
+				fn $method(&self $(, $arg: $ty)*) -> $out;
+			)*
+
+			/// Transforms the object into `IoDelegate` with every rpc method registered.
+			fn to_delegate(self) -> $crate::IoDelegate<Self> {
+				let mut delegate = $crate::IoDelegate::new(::std::sync::Arc::new(self));
+				$(
+					delegate.add_method($rpc_name, |base: &Self, params: $crate::Params| {
+						let ($($arg,)*): ($($ty,)*) = try!(params.parse());
+						base.$method($($arg),*)
+							.map(|result| $crate::macros::to_value(&result))
+							.map_err(Into::into)
+					});
+				)*
+				delegate
+			}
+		}
+
+		/// Client stub creating method calls.
+		pub struct $client;
+
+		impl $client {
+			$(
+				pub fn $method<T>(manager: &mut $crate::client::RequestManager<T>, caller: T $(, $arg: $ty)*) -> $crate::MethodCall {
+					let params = $crate::Params::Array(vec![$($crate::macros::to_value(&$arg)),*]);
+					manager.method_call($rpc_name, params, caller)
+				}
+			)*
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::*;
+	use super::super::client::RequestManager;
+
+	build_rpc_trait! {
+		pub trait Eth {
+			#[rpc(name = "eth_blockNumber")]
+			fn block_number(&self) -> Result<u64, Error>;
+
+			#[rpc(name = "eth_getBalance")]
+			/// Returns balance of the account.
+			fn balance(&self, address: String, block: u64) -> Result<u64, Error>;
+		}
+
+		pub client EthClient;
+	}
+
+	struct EthImpl;
+
+	impl Eth for EthImpl {
+		fn block_number(&self) -> Result<u64, Error> {
+			Ok(10)
+		}
+
+		fn balance(&self, address: String, block: u64) -> Result<u64, Error> {
+			Ok(address.len() as u64 + block)
+		}
+	}
+
+	#[test]
+	fn test_rpc_trait() {
+		let mut io = IoHandler::new();
+		io.add_delegate(EthImpl.to_delegate());
+
+		let request = r#"{"jsonrpc": "2.0", "method": "eth_blockNumber", "id": 1}"#;
+		let response = r#"{"jsonrpc":"2.0","result":10,"id":1}"#;
+		assert_eq!(io.handle_request(request), Some(response.to_string()));
+
+		let request = r#"{"jsonrpc": "2.0", "method": "eth_getBalance", "params": ["abc", 2], "id": 2}"#;
+		let response = r#"{"jsonrpc":"2.0","result":5,"id":2}"#;
+		assert_eq!(io.handle_request(request), Some(response.to_string()));
+	}
+
+	#[test]
+	fn test_rpc_client() {
+		let mut manager = RequestManager::new();
+		let call = EthClient::balance(&mut manager, (), "abc".to_owned(), 2);
+
+		assert_eq!(call, MethodCall::new("eth_getBalance", Params::Array(vec![
+			Value::String("abc".to_owned()), Value::U64(2)
+		]), Id::Num(0)));
+	}
+}
@@ -0,0 +1,8 @@
+//! jsonrpc request metadata
+
+/// Request metadata, e.g. peer address, auth token or session.
+///
+/// It's attached to the request by transport and passed to every command.
+pub trait Metadata: Default + Clone + Send + 'static {}
+
+impl Metadata for () {}
@@ -0,0 +1,108 @@
+//! jsonrpc request handling middleware
+use super::{Request, Response, Call, Output, Metadata};
+
+/// Wraps request and call handling.
+///
+/// Middlewares are called in the order they were added before
+/// the call and in reverse order after it.
+pub trait Middleware<M: Metadata = ()>: Send + Sync {
+	/// Called before request is handled. May modify or replace the request.
+	fn on_request(&self, request: Request, _meta: &M) -> Request {
+		request
+	}
+
+	/// Called before call is executed. May modify or replace the call.
+	/// Returning `Err` short-circuits the call with given output,
+	/// so following middlewares and the command are never executed.
+	fn on_call(&self, call: Call, _meta: &M) -> Result<Call, Option<Output>> {
+		Ok(call)
+	}
+
+	/// Called after call is executed. May modify or replace the output.
+	fn on_output(&self, output: Option<Output>) -> Option<Output> {
+		output
+	}
+
+	/// Called after request is handled. May modify or replace the response.
+	fn on_response(&self, response: Option<Response>) -> Option<Response> {
+		response
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use super::*;
+	use super::super::*;
+
+	struct Counter(Arc<AtomicUsize>);
+
+	impl Middleware<Session> for Counter {
+		fn on_output(&self, output: Option<Output>) -> Option<Output> {
+			self.0.fetch_add(1, Ordering::SeqCst);
+			output
+		}
+	}
+
+	#[derive(Default, Clone)]
+	struct Session {
+		admin: bool
+	}
+
+	impl Metadata for Session {}
+
+	struct Auth;
+
+	impl Middleware<Session> for Auth {
+		fn on_call(&self, call: Call, session: &Session) -> Result<Call, Option<Output>> {
+			match call {
+				Call::MethodCall(ref m) if m.method.starts_with("admin_") && !session.admin => Err(Some(Output::Failure(Failure {
+					jsonrpc: Version::V2,
+					error: Error::new(ErrorCode::ServerError(-32000)),
+					id: m.id.clone()
+				}))),
+				call => Ok(call)
+			}
+		}
+	}
+
+	struct Rename;
+
+	impl Middleware<Session> for Rename {
+		fn on_call(&self, call: Call, _session: &Session) -> Result<Call, Option<Output>> {
+			match call {
+				Call::MethodCall(mut m) => {
+					if m.method == "hi" {
+						m.method = "say_hello".to_owned();
+					}
+					Ok(Call::MethodCall(m))
+				},
+				call => Ok(call)
+			}
+		}
+	}
+
+	#[test]
+	fn test_middlewares() {
+		let counter = Arc::new(AtomicUsize::new(0));
+		let mut io = MetaIoHandler::new();
+		io.add_method("say_hello", |_params: Params| -> Result<Value, Error> { Ok(Value::String("hello".to_owned())) });
+		io.add_method("admin_reset", |_params: Params| -> Result<Value, Error> { Ok(Value::Null) });
+		io.add_middleware(Counter(counter.clone()));
+		io.add_middleware(Auth);
+		io.add_middleware(Rename);
+
+		let request = r#"[
+			{"jsonrpc": "2.0", "method": "hi", "id": 1},
+			{"jsonrpc": "2.0", "method": "admin_reset", "id": 2}
+		]"#;
+		let response = r#"[{"jsonrpc":"2.0","result":"hello","id":1},{"jsonrpc":"2.0","error":{"code":-32000,"message":"Server error.","data":null},"id":2}]"#;
+
+		assert_eq!(io.handle_request_with_meta(request, Session::default()), Some(response.to_owned()));
+		assert_eq!(counter.load(Ordering::SeqCst), 2);
+
+		let response = r#"[{"jsonrpc":"2.0","result":"hello","id":1},{"jsonrpc":"2.0","result":null,"id":2}]"#;
+		assert_eq!(io.handle_request_with_meta(request, Session { admin: true }), Some(response.to_owned()));
+	}
+}
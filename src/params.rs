@@ -1,9 +1,11 @@
 //! jsonrpc params field
-use std::collections::HashMap;
+use std::collections::{HashMap, BTreeMap};
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
 use serde::de::{Visitor, SeqVisitor, MapVisitor};
 use serde::de::impls::{VecVisitor, HashMapVisitor};
-use super::Value;
+use serde_json;
+use serde_json::value::from_value;
+use super::{Value, Error};
 
 #[derive(Debug, PartialEq)]
 pub enum Params {
@@ -12,12 +14,51 @@ pub enum Params {
 	None
 }
 
+impl Params {
+	/// Deserializes params into typed value.
+	///
+	/// Positional params can be parsed into tuples or structs,
+	/// named params into structs. Missing params are parsed as empty
+	/// positional params or, if that fails, as empty named params,
+	/// so structs with only optional fields accept them too.
+	///
+	/// ```rust
+	/// extern crate jsonrpc_core;
+	/// use jsonrpc_core::*;
+	///
+	/// fn main() {
+	/// 	let params = Params::Array(vec![Value::U64(42), Value::String("hello".to_owned())]);
+	/// 	let (a, b): (u64, String) = params.parse().unwrap();
+	/// 	assert_eq!(a, 42);
+	/// 	assert_eq!(b, "hello".to_owned());
+	/// }
+	/// ```
+	pub fn parse<D>(self) -> Result<D, Error> where D: Deserialize {
+		let value = match self {
+			Params::Array(vec) => Value::Array(vec),
+			Params::Map(map) => Value::Object(map.into_iter().collect()),
+			Params::None => return from_value(Value::Array(vec![]))
+				.or_else(|e| from_value(Value::Object(BTreeMap::new())).map_err(|_| e))
+				.map_err(invalid_params)
+		};
+
+		from_value(value).map_err(invalid_params)
+	}
+}
+
+fn invalid_params(e: serde_json::Error) -> Error {
+	let mut error = Error::invalid_params();
+	error.data = Some(Value::String(format!("{}", e)));
+	error
+}
+
 impl Serialize for Params {
 	fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error> 
 	where S: Serializer {
 		match *self {
 			Params::Array(ref vec) => vec.serialize(serializer),
-			Params::Map(ref map) => map.serialize(serializer),
+			// sorted, so serialized params are always the same
+			Params::Map(ref map) => map.iter().collect::<BTreeMap<_, _>>().serialize(serializer),
 			Params::None => ([] as [u8; 0]).serialize(serializer)
 		}
 	}
@@ -69,3 +110,74 @@ fn params_deserialization() {
 							 Value::F64(2.3), Value::String("hello".to_string()),
 							 Value::Array(vec![Value::U64(0)]), Value::Object(map)]), deserialized);
 }
+
+#[test]
+fn params_parse() {
+	use super::ErrorCode;
+
+	#[derive(Debug, PartialEq, Deserialize)]
+	struct Point {
+		x: u64,
+		y: u64
+	}
+
+	let params = Params::Array(vec![Value::U64(1), Value::U64(2)]);
+	let parsed: (u64, u64) = params.parse().unwrap();
+	assert_eq!(parsed, (1, 2));
+
+	let params = Params::Array(vec![Value::U64(1), Value::U64(2)]);
+	let parsed: Point = params.parse().unwrap();
+	assert_eq!(parsed, Point { x: 1, y: 2 });
+
+	let mut map = HashMap::new();
+	map.insert("x".to_owned(), Value::U64(1));
+	map.insert("y".to_owned(), Value::U64(2));
+	let parsed: Point = Params::Map(map).parse().unwrap();
+	assert_eq!(parsed, Point { x: 1, y: 2 });
+
+	let params = Params::Array(vec![Value::String("1".to_owned())]);
+	let error = params.parse::<(u64,)>().unwrap_err();
+	assert_eq!(error.code, ErrorCode::InvalidParams);
+	assert!(error.data.is_some());
+}
+
+#[test]
+fn params_parse_none() {
+	use super::ErrorCode;
+
+	#[derive(Debug, PartialEq, Deserialize)]
+	struct Options {
+		limit: Option<u64>,
+		verbose: Option<bool>
+	}
+
+	let parsed: Options = Params::None.parse().unwrap();
+	assert_eq!(parsed, Options { limit: None, verbose: None });
+
+	let error = Params::None.parse::<(u64,)>().unwrap_err();
+	assert_eq!(error.code, ErrorCode::InvalidParams);
+}
+
+#[cfg(test)]
+impl super::rng::Rng {
+	/// Params are never empty, because empty ones are read as `Params::None`.
+	pub fn params(&mut self) -> Option<Params> {
+		match self.below(3) {
+			0 => None,
+			1 => Some(Params::Array((0..self.below(4) + 1).map(|_| self.value(2)).collect())),
+			_ => Some(Params::Map((0..self.below(4) + 1).map(|i| (format!("{}{}", self.string(), i), self.value(2))).collect::<HashMap<_, _>>()))
+		}
+	}
+}
+
+#[test]
+fn params_roundtrip() {
+	use super::rng::{Rng, ITERATIONS, assert_roundtrip};
+
+	let mut rng = Rng(0x853c49e6748fea9b);
+	for _ in 0..ITERATIONS {
+		if let Some(params) = rng.params() {
+			assert_roundtrip(params);
+		}
+	}
+}
@@ -0,0 +1,74 @@
+//! thread pool used for concurrent batch execution
+use std::{panic, thread};
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex, mpsc};
+
+trait FnBox {
+	fn call_box(self: Box<Self>);
+}
+
+impl<F> FnBox for F where F: FnOnce() {
+	fn call_box(self: Box<F>) {
+		(*self)()
+	}
+}
+
+type Job = Box<FnBox + Send>;
+
+/// Fixed size thread pool.
+///
+/// Worker survives panicking job, so the pool never shrinks.
+pub struct ThreadPool {
+	sender: Option<Mutex<mpsc::Sender<Job>>>,
+	workers: Vec<thread::JoinHandle<()>>
+}
+
+impl ThreadPool {
+	pub fn new(threads: usize) -> Self {
+		assert!(threads > 0, "thread pool requires at least one thread");
+
+		let (tx, rx) = mpsc::channel::<Job>();
+		let rx = Arc::new(Mutex::new(rx));
+		let workers = (0..threads).map(|_| {
+			let rx = rx.clone();
+			thread::spawn(move || loop {
+				let job = match rx.lock() {
+					Ok(rx) => rx.recv(),
+					Err(_) => break
+				};
+
+				match job {
+					// job which panics drops what it captured, e.g. `Ready` completes with internal error
+					Ok(job) => {
+						let _ = panic::catch_unwind(AssertUnwindSafe(move || job.call_box()));
+					},
+					Err(_) => break
+				}
+			})
+		}).collect();
+
+		ThreadPool {
+			sender: Some(Mutex::new(tx)),
+			workers: workers
+		}
+	}
+
+	pub fn execute<F>(&self, job: F) where F: FnOnce() + Send + 'static {
+		if let Some(ref sender) = self.sender {
+			// workers are alive as long as sender exists
+			if let Ok(sender) = sender.lock() {
+				let _ = sender.send(Box::new(job));
+			}
+		}
+	}
+}
+
+impl Drop for ThreadPool {
+	fn drop(&mut self) {
+		// closing the channel stops every worker
+		self.sender.take();
+		for worker in self.workers.drain(..) {
+			let _ = worker.join();
+		}
+	}
+}
@@ -0,0 +1,288 @@
+//! jsonrpc publish/subscribe
+use std::collections::HashMap;
+use std::sync::{Arc, Weak, Mutex, mpsc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use serde_json;
+use serde_json::value::to_value;
+use super::*;
+
+/// Connection with a single client.
+///
+/// Transport should create a session for every connection and close it
+/// when the connection is closed, since server code may still hold it.
+/// Every subscription of the session is unsubscribed once it's closed or dropped.
+pub struct Session {
+	sender: Mutex<Option<mpsc::Sender<String>>>,
+	subscriptions: Mutex<HashMap<(String, Id), Box<Fn() + Send>>>
+}
+
+impl Session {
+	/// Creates new session. Server-initiated messages are sent to `sender`.
+	pub fn new(sender: mpsc::Sender<String>) -> Self {
+		Session {
+			sender: Mutex::new(Some(sender)),
+			subscriptions: Mutex::new(HashMap::new())
+		}
+	}
+
+	/// Sends message to the client.
+	pub fn send(&self, message: String) -> Result<(), SessionClosed> {
+		match self.sender.lock() {
+			Ok(sender) => match *sender {
+				Some(ref sender) => sender.send(message).map_err(|_| SessionClosed),
+				None => Err(SessionClosed)
+			},
+			Err(_) => Err(SessionClosed)
+		}
+	}
+
+	/// Closes the session. Messages can't be sent anymore
+	/// and every subscription is unsubscribed.
+	///
+	/// Drops the sender, so the transport receiving messages
+	/// is not kept alive by anyone holding the session.
+	pub fn close(&self) {
+		if let Ok(mut sender) = self.sender.lock() {
+			sender.take();
+		}
+
+		let subscriptions: Vec<_> = match self.subscriptions.lock() {
+			Ok(mut subscriptions) => subscriptions.drain().map(|(_, unsubscribe)| unsubscribe).collect(),
+			Err(_) => return
+		};
+
+		for unsubscribe in subscriptions {
+			unsubscribe();
+		}
+	}
+
+	/// Returns true if the session is closed.
+	pub fn is_closed(&self) -> bool {
+		self.sender.lock().map(|sender| sender.is_none()).unwrap_or(true)
+	}
+
+	fn add_subscription(&self, name: &str, id: &Id, unsubscribe: Box<Fn() + Send>) {
+		let mut subscriptions = self.subscriptions.lock().unwrap();
+		// session closed meanwhile, nothing would unsubscribe it later
+		if self.is_closed() {
+			drop(subscriptions);
+			unsubscribe();
+			return;
+		}
+		subscriptions.insert((name.to_owned(), id.clone()), unsubscribe);
+	}
+
+	fn remove_subscription(&self, name: &str, id: &Id) -> Option<Box<Fn() + Send>> {
+		self.subscriptions.lock().unwrap().remove(&(name.to_owned(), id.clone()))
+	}
+}
+
+impl Drop for Session {
+	fn drop(&mut self) {
+		self.close()
+	}
+}
+
+/// Returned when message can't be sent, because the session is closed.
+#[derive(Debug, PartialEq)]
+pub struct SessionClosed;
+
+/// Metadata which gives access to the session of the request.
+pub trait PubSubMetadata: Metadata {
+	fn session(&self) -> Option<Arc<Session>>;
+}
+
+impl Metadata for Option<Arc<Session>> {}
+
+impl PubSubMetadata for Option<Arc<Session>> {
+	fn session(&self) -> Option<Arc<Session>> {
+		self.clone()
+	}
+}
+
+/// Sends notifications of a single subscription to the client.
+pub struct Sink {
+	notification: String,
+	id: Id,
+	session: Weak<Session>
+}
+
+impl Sink {
+	/// Subscription id.
+	pub fn id(&self) -> &Id {
+		&self.id
+	}
+
+	/// Sends `{"subscription": id, "result": result}` notification to the client.
+	pub fn notify(&self, result: Value) -> Result<(), SessionClosed> {
+		let session = try!(self.session.upgrade().ok_or(SessionClosed));
+
+		let mut params = HashMap::new();
+		params.insert("subscription".to_owned(), to_value(&self.id));
+		params.insert("result".to_owned(), result);
+
+		let notification = Notification::new(&self.notification, Params::Map(params));
+		// this should never fail
+		session.send(serde_json::to_string(&notification).unwrap())
+	}
+}
+
+fn subscriptions_not_supported() -> Error {
+	let mut error = Error::new(ErrorCode::ServerError(-32090));
+	error.message = "Subscriptions are not supported.".to_owned();
+	error
+}
+
+impl<M> MetaIoHandler<M> where M: PubSubMetadata {
+	/// Adds subscribe and unsubscribe method pair.
+	///
+	/// `subscribe` is called with a `Sink` used to send `notification`s to the client.
+	/// Subscription is registered only if `subscribe` succeeds, otherwise its error
+	/// is returned to the client. `unsubscribe` is called with subscription id when the client unsubscribes
+	/// or its session ends.
+	///
+	/// ```rust
+	/// extern crate jsonrpc_core;
+	/// use std::sync::{Arc, Mutex, mpsc};
+	/// use jsonrpc_core::*;
+	/// use jsonrpc_core::pubsub::*;
+	///
+	/// fn main() {
+	/// 	let sinks = Arc::new(Mutex::new(Vec::new()));
+	/// 	let mut io = MetaIoHandler::<Option<Arc<Session>>>::new();
+	/// 	let s = sinks.clone();
+	/// 	io.add_subscription("hello", "hello_subscribe", "hello_unsubscribe",
+	/// 		move |_params, sink| {
+	/// 			s.lock().unwrap().push(sink);
+	/// 			Ok(())
+	/// 		},
+	/// 		|_id| {}
+	/// 	);
+	///
+	/// 	let (tx, rx) = mpsc::channel();
+	/// 	let session = Some(Arc::new(Session::new(tx)));
+	/// 	let request = r#"{"jsonrpc": "2.0", "method": "hello_subscribe", "id": 1}"#;
+	/// 	let response = r#"{"jsonrpc":"2.0","result":0,"id":1}"#;
+	/// 	assert_eq!(io.handle_request_with_meta(request, session.clone()), Some(response.to_string()));
+	///
+	/// 	sinks.lock().unwrap()[0].notify(Value::String("world".to_owned())).unwrap();
+	/// 	let notification = r#"{"jsonrpc":"2.0","method":"hello","params":{"result":"world","subscription":0}}"#;
+	/// 	assert_eq!(rx.recv().unwrap(), notification.to_owned());
+	/// }
+	/// ```
+	pub fn add_subscription<S, U>(&mut self, notification: &str, subscribe_name: &str, unsubscribe_name: &str, subscribe: S, unsubscribe: U) where
+		S: Fn(Params, Sink) -> Result<(), Error> + Send + Sync + 'static,
+		U: Fn(Id) + Send + Sync + 'static {
+		let next_id = Arc::new(AtomicUsize::new(0));
+		let unsubscribe = Arc::new(unsubscribe);
+
+		let name = notification.to_owned();
+		let on_unsubscribe = unsubscribe.clone();
+		self.add_meta_method(subscribe_name, move |params: Params, meta: M| -> Result<Value, Error> {
+			let session = try!(meta.session().ok_or_else(subscriptions_not_supported));
+			let id = Id::Num(next_id.fetch_add(1, Ordering::SeqCst) as u64);
+
+			try!(subscribe(params, Sink {
+				notification: name.clone(),
+				id: id.clone(),
+				session: Arc::downgrade(&session)
+			}));
+
+			let unsubscribe = on_unsubscribe.clone();
+			let unsubscribe_id = id.clone();
+			session.add_subscription(&name, &id, Box::new(move || (*unsubscribe)(unsubscribe_id.clone())));
+			Ok(to_value(&id))
+		});
+
+		let name = notification.to_owned();
+		self.add_meta_method(unsubscribe_name, move |params: Params, meta: M| -> Result<Value, Error> {
+			let session = try!(meta.session().ok_or_else(subscriptions_not_supported));
+			let (id,): (Id,) = try!(params.parse());
+			match session.remove_subscription(&name, &id) {
+				Some(_) => {
+					(*unsubscribe)(id);
+					Ok(Value::Bool(true))
+				},
+				None => Ok(Value::Bool(false))
+			}
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex, mpsc};
+	use super::*;
+	use super::super::*;
+
+	#[test]
+	fn test_subscriptions() {
+		let sinks = Arc::new(Mutex::new(Vec::new()));
+		let unsubscribed = Arc::new(Mutex::new(Vec::new()));
+
+		let mut io = MetaIoHandler::<Option<Arc<Session>>>::new();
+		let s = sinks.clone();
+		let u = unsubscribed.clone();
+		io.add_subscription("hello", "hello_subscribe", "hello_unsubscribe",
+			move |_params, sink| {
+				s.lock().unwrap().push(sink);
+				Ok(())
+			},
+			move |id| u.lock().unwrap().push(id)
+		);
+
+		let (tx, rx) = mpsc::channel();
+		let session = Some(Arc::new(Session::new(tx)));
+
+		let request = r#"[
+			{"jsonrpc": "2.0", "method": "hello_subscribe", "id": 1},
+			{"jsonrpc": "2.0", "method": "hello_subscribe", "id": 2}
+		]"#;
+		let response = r#"[{"jsonrpc":"2.0","result":0,"id":1},{"jsonrpc":"2.0","result":1,"id":2}]"#;
+		assert_eq!(io.handle_request_with_meta(request, session.clone()), Some(response.to_owned()));
+
+		sinks.lock().unwrap()[1].notify(Value::U64(5)).unwrap();
+		assert_eq!(rx.recv().unwrap(), r#"{"jsonrpc":"2.0","method":"hello","params":{"result":5,"subscription":1}}"#.to_owned());
+
+		let request = r#"{"jsonrpc": "2.0", "method": "hello_unsubscribe", "params": [0], "id": 3}"#;
+		let response = r#"{"jsonrpc":"2.0","result":true,"id":3}"#;
+		assert_eq!(io.handle_request_with_meta(request, session.clone()), Some(response.to_owned()));
+		assert_eq!(*unsubscribed.lock().unwrap(), vec![Id::Num(0)]);
+
+		// session end unsubscribes the rest
+		drop(session);
+		assert_eq!(*unsubscribed.lock().unwrap(), vec![Id::Num(0), Id::Num(1)]);
+		assert_eq!(sinks.lock().unwrap()[1].notify(Value::U64(5)), Err(SessionClosed));
+	}
+
+	#[test]
+	fn test_subscriptions_without_session() {
+		let mut io = MetaIoHandler::<Option<Arc<Session>>>::new();
+		io.add_subscription("hello", "hello_subscribe", "hello_unsubscribe", |_params, _sink| Ok(()), |_id| {});
+
+		let request = r#"{"jsonrpc": "2.0", "method": "hello_subscribe", "id": 1}"#;
+		let response = io.handle_request(request).unwrap();
+		assert!(response.contains("-32090"));
+	}
+
+	#[test]
+	fn test_failed_subscription() {
+		let unsubscribed = Arc::new(Mutex::new(Vec::new()));
+		let mut io = MetaIoHandler::<Option<Arc<Session>>>::new();
+		let u = unsubscribed.clone();
+		io.add_subscription("hello", "hello_subscribe", "hello_unsubscribe",
+			|_params, _sink| Err(Error::invalid_params()),
+			move |id| u.lock().unwrap().push(id)
+		);
+
+		let (tx, _rx) = mpsc::channel();
+		let session = Some(Arc::new(Session::new(tx)));
+		let request = r#"{"jsonrpc": "2.0", "method": "hello_subscribe", "id": 1}"#;
+		let response = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params.","data":null},"id":1}"#;
+		assert_eq!(io.handle_request_with_meta(request, session.clone()), Some(response.to_owned()));
+
+		// nothing was registered, so nothing is unsubscribed
+		drop(session);
+		assert!(unsubscribed.lock().unwrap().is_empty());
+	}
+}
@@ -1,10 +1,11 @@
 //! jsonrpc request
-use serde::de::{Deserialize, Deserializer, Visitor, SeqVisitor, MapVisitor};
+use serde::{Serialize, Serializer};
+use serde::de::{Deserialize, Deserializer};
+use serde_json::value::from_value;
 use super::{Id, Params, Version, Value};
-use super::peek::*;
 
 /// Represents jsonrpc request which is a method call.
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct MethodCall {
 	/// A String specifying the version of the JSON-RPC protocol. 
 	/// MUST be exactly "2.0".
@@ -13,6 +14,7 @@ pub struct MethodCall {
 	pub method: String,
 	/// A Structured value that holds the parameter values to be used 
 	/// during the invocation of the method. This member MAY be omitted.
+	#[serde(skip_serializing_if_none)]
 	pub params: Option<Params>,
 	/// An identifier established by the Client that MUST contain a String,
 	/// Number, or NULL value if included. If it is not included it is assumed 
@@ -21,7 +23,7 @@ pub struct MethodCall {
 }
 
 /// Represents jsonrpc request which is a notification.
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Notification {
 	/// A String specifying the version of the JSON-RPC protocol. 
 	/// MUST be exactly "2.0".
@@ -30,23 +32,136 @@ pub struct Notification {
 	pub method: String,
 	/// A Structured value that holds the parameter values to be used 
 	/// during the invocation of the method. This member MAY be omitted.
+	#[serde(skip_serializing_if_none)]
 	pub params: Option<Params>
 }
 
+impl MethodCall {
+	pub fn new(method: &str, params: Params, id: Id) -> Self {
+		MethodCall {
+			jsonrpc: Version::V2,
+			method: method.to_owned(),
+			params: Some(params),
+			id: id
+		}
+	}
+}
+
+impl Notification {
+	pub fn new(method: &str, params: Params) -> Self {
+		Notification {
+			jsonrpc: Version::V2,
+			method: method.to_owned(),
+			params: Some(params)
+		}
+	}
+}
+
 /// Represents single jsonrpc call.
 #[derive(Debug, PartialEq)]
 pub enum Call {
 	MethodCall(MethodCall),
 	Notification(Notification),
-	Invalid
+	/// Value which is neither a method call nor a notification.
+	Invalid(Value)
+}
+
+impl Serialize for Call {
+	fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+	where S: Serializer {
+		match *self {
+			Call::MethodCall(ref m) => m.serialize(serializer),
+			Call::Notification(ref n) => n.serialize(serializer),
+			Call::Invalid(ref value) => value.serialize(serializer)
+		}
+	}
+}
+
+impl Call {
+	/// Explains why call is invalid, or returns `None` if it's not.
+	pub fn invalid_reason(&self) -> Option<&'static str> {
+		match *self {
+			Call::Invalid(ref value) => Some(invalid_reason(value).unwrap_or("Invalid request object.")),
+			_ => None
+		}
+	}
 }
 
+/// Explains why value is not a valid call, or returns `None` if it is.
+fn invalid_reason(value: &Value) -> Option<&'static str> {
+	let object = match *value {
+		Value::Object(ref object) => object,
+		_ => return Some("Request must be an object.")
+	};
+
+	if object.get("jsonrpc") != Some(&Value::String("2.0".to_owned())) {
+		return Some("`jsonrpc` must be exactly \"2.0\".");
+	}
+
+	match object.get("method") {
+		Some(&Value::String(_)) => {},
+		Some(_) => return Some("`method` must be a string."),
+		None => return Some("`method` is missing.")
+	}
+
+	match object.get("params") {
+		None | Some(&Value::Null) | Some(&Value::Array(_)) | Some(&Value::Object(_)) => {},
+		Some(_) => return Some("`params` must be an array or an object.")
+	}
+
+	if let Some(Err(_)) = object.get("id").map(|id| from_value::<Id>(id.clone())) {
+		return Some("`id` must be a string, an integer or null.");
+	}
+
+	match object.keys().find(|key| !["jsonrpc", "method", "params", "id"].contains(&(key as &str))) {
+		Some(_) => Some("Request contains unknown field."),
+		None => None
+	}
+}
+
+impl From<Value> for Call {
+	/// Classifies parsed value as a method call, a notification or an invalid call.
+	fn from(value: Value) -> Self {
+		if invalid_reason(&value).is_some() {
+			return Call::Invalid(value);
+		}
+
+		let mut object = match value {
+			Value::Object(object) => object,
+			value => return Call::Invalid(value)
+		};
+
+		// every field was validated above
+		let method = match object.remove("method") {
+			Some(Value::String(method)) => method,
+			_ => String::new()
+		};
+		let params = match object.remove("params") {
+			None | Some(Value::Null) => None,
+			Some(params) => from_value(params).ok()
+		};
+
+		match object.remove("id").and_then(|id| from_value(id).ok()) {
+			Some(id) => Call::MethodCall(MethodCall {
+				jsonrpc: Version::V2,
+				method: method,
+				params: params,
+				id: id
+			}),
+			None => Call::Notification(Notification {
+				jsonrpc: Version::V2,
+				method: method,
+				params: params
+			})
+		}
+	}
+}
+
+/// Parses value once and classifies it.
 impl Deserialize for Call {
 	fn deserialize<D>(deserializer: &mut D) -> Result<Call, D::Error>
 	where D: Deserializer {
-		Notification::peek(deserializer).map(Call::Notification)
-			.or_else(|_| MethodCall::peek(deserializer).map(Call::MethodCall))
-			.or_else(|_| Value::deserialize(deserializer).map(|_| Call::Invalid))
+		Value::deserialize(deserializer).map(Call::from)
 	}
 }
 
@@ -57,11 +172,30 @@ pub enum Request {
 	Batch(Vec<Call>)
 }
 
+impl Serialize for Request {
+	fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+	where S: Serializer {
+		match *self {
+			Request::Single(ref call) => call.serialize(serializer),
+			Request::Batch(ref calls) => calls.serialize(serializer)
+		}
+	}
+}
+
+impl From<Value> for Request {
+	fn from(value: Value) -> Self {
+		match value {
+			Value::Array(values) => Request::Batch(values.into_iter().map(Call::from).collect()),
+			value => Request::Single(Call::from(value))
+		}
+	}
+}
+
+/// Parses value once and classifies it.
 impl Deserialize for Request {
 	fn deserialize<D>(deserializer: &mut D) -> Result<Request, D::Error>
 	where D: Deserializer {
-		<Vec<Call> as Peek>::peek(deserializer).map(Request::Batch)
-			.or_else(|_| Call::deserialize(deserializer).map(Request::Single))
+		Value::deserialize(deserializer).map(Request::from)
 	}
 }
 
@@ -93,6 +227,15 @@ fn notification_deserialize() {
 	assert!(deserialized.is_err())
 }
 
+#[test]
+fn method_call_serialize() {
+	use serde_json;
+
+	let m = MethodCall::new("update", Params::Array(vec![Value::U64(1), Value::U64(2)]), Id::Num(1));
+	let serialized = serde_json::to_string(&m).unwrap();
+	assert_eq!(serialized, r#"{"jsonrpc":"2.0","method":"update","params":[1,2],"id":1}"#);
+}
+
 #[test]
 fn call_deserialize_batch() {
 	use serde_json;
@@ -100,7 +243,7 @@ fn call_deserialize_batch() {
 	let s = r#"[1, {"jsonrpc": "2.0", "method": "update", "params": [1,2], "id": 1},{"jsonrpc": "2.0", "method": "update", "params": [1]}]"#;
 	let deserialized: Request = serde_json::from_str(s).unwrap();
 	assert_eq!(deserialized, Request::Batch(vec![
-		Call::Invalid,
+		Call::Invalid(Value::U64(1)),
 		Call::MethodCall(MethodCall {
 			jsonrpc: Version::V2,
 			method: "update".to_owned(),
@@ -114,3 +257,75 @@ fn call_deserialize_batch() {
 		})
 	]))
 }
+
+#[test]
+fn call_classify() {
+	use serde_json;
+
+	let s = r#"[{"jsonrpc": "2.0", "method": "update", "id": null}, {"jsonrpc": "2.0", "method": "update", "params": null}, {"jsonrpc": "2.0", "method": "update", "id": 1.5}]"#;
+	let deserialized: Request = serde_json::from_str(s).unwrap();
+	let invalid: Value = serde_json::from_str(r#"{"jsonrpc": "2.0", "method": "update", "id": 1.5}"#).unwrap();
+	assert_eq!(deserialized, Request::Batch(vec![
+		Call::MethodCall(MethodCall {
+			jsonrpc: Version::V2,
+			method: "update".to_owned(),
+			params: None,
+			id: Id::Null
+		}),
+		Call::Notification(Notification {
+			jsonrpc: Version::V2,
+			method: "update".to_owned(),
+			params: None
+		}),
+		Call::Invalid(invalid)
+	]));
+}
+
+#[cfg(test)]
+impl super::rng::Rng {
+	pub fn call(&mut self) -> Call {
+		use std::collections::BTreeMap;
+
+		match self.below(3) {
+			0 => Call::MethodCall(MethodCall {
+				jsonrpc: Version::V2,
+				method: self.string(),
+				params: self.params(),
+				id: self.id()
+			}),
+			1 => Call::Notification(Notification {
+				jsonrpc: Version::V2,
+				method: self.string(),
+				params: self.params()
+			}),
+			_ => match self.below(2) {
+				0 => Call::Invalid(Value::U64(self.next())),
+				_ => {
+					let mut object = BTreeMap::new();
+					object.insert("jsonrpc".to_owned(), Value::String("2.0".to_owned()));
+					object.insert("method".to_owned(), Value::U64(self.next()));
+					object.insert("id".to_owned(), Value::String(self.string()));
+					Call::Invalid(Value::Object(object))
+				}
+			}
+		}
+	}
+
+	pub fn request(&mut self) -> Request {
+		match self.below(2) {
+			0 => Request::Single(self.call()),
+			_ => Request::Batch((0..self.below(5)).map(|_| self.call()).collect())
+		}
+	}
+}
+
+#[test]
+fn request_roundtrip() {
+	use super::rng::{Rng, ITERATIONS, assert_roundtrip};
+
+	let mut rng = Rng(0x2545f4914f6cdd1d);
+	for _ in 0..ITERATIONS {
+		assert_roundtrip(rng.call());
+		assert_roundtrip(rng.request());
+	}
+}
@@ -1,15 +1,114 @@
 //! jsonrpc server request handler
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex, mpsc};
+use serde_json::value::from_value;
 use super::*;
+use super::pool::ThreadPool;
 
-pub struct RequestHandler {
-	commander: Commander
+/// Middlewares applied to every request and call.
+struct Middlewares<M: Metadata>(Vec<Arc<Box<Middleware<M>>>>);
+
+impl<M> Clone for Middlewares<M> where M: Metadata {
+	fn clone(&self) -> Self {
+		Middlewares(self.0.clone())
+	}
 }
 
-impl RequestHandler {
+impl<M> Middlewares<M> where M: Metadata {
+	fn on_request(&self, request: Request, meta: &M) -> Request {
+		self.0.iter().fold(request, |request, m| m.on_request(request, meta))
+	}
+
+	fn on_response(&self, response: Option<Response>) -> Option<Response> {
+		self.0.iter().rev().fold(response, |response, m| m.on_response(response))
+	}
+
+	/// Passes call through middlewares. If one of them short-circuits,
+	/// returns its output with the number of middlewares it was passed through.
+	fn before(&self, mut call: Call, meta: &M) -> Result<Call, (usize, Option<Output>)> {
+		for (i, m) in self.0.iter().enumerate() {
+			call = match m.on_call(call, meta) {
+				Ok(call) => call,
+				Err(output) => return Err((i, output))
+			};
+		}
+		Ok(call)
+	}
+
+	/// Passes output through first `passed` middlewares in reverse order.
+	fn after(&self, passed: usize, output: Option<Output>) -> Option<Output> {
+		self.0[..passed].iter().rev().fold(output, |output, m| m.on_output(output))
+	}
+
+	fn len(&self) -> usize {
+		self.0.len()
+	}
+}
+
+/// Outputs of asynchronously handled request.
+struct AsyncOutputs<F> {
+	outputs: Vec<Option<Output>>,
+	pending: usize,
+	batch: bool,
+	on_response: Option<F>
+}
+
+impl<F> AsyncOutputs<F> where F: FnOnce(Option<Response>) {
+	/// Stores output of a call and passes response to `on_response` once
+	/// every call is done.
+	fn complete(state: &Mutex<Self>, output: Option<(usize, Option<Output>)>) {
+		let finished = {
+			let mut state = state.lock().unwrap();
+			if let Some((index, output)) = output {
+				state.outputs[index] = output;
+			}
+			state.pending -= 1;
+			match state.pending {
+				0 => {
+					let outs: Vec<Output> = state.outputs.drain(..).filter_map(|o| o).collect();
+					let response = match (state.batch, outs.len()) {
+						(_, 0) => None,
+						(true, _) => Some(Response::Batch(outs)),
+						(false, _) => outs.into_iter().next().map(Response::Single)
+					};
+					state.on_response.take().map(|on_response| (on_response, response))
+				},
+				_ => None
+			}
+		};
+
+		if let Some((on_response, response)) = finished {
+			on_response(response)
+		}
+	}
+}
+
+pub struct RequestHandler<M: Metadata = ()> {
+	commander: Commander<M>,
+	middlewares: Middlewares<M>,
+	pool: Option<ThreadPool>
+}
+
+impl<M> RequestHandler<M> where M: Metadata {
 	pub fn new() -> Self {
 		RequestHandler {
-			commander: Commander::new()
+			commander: Commander::new(),
+			middlewares: Middlewares(vec![]),
+			pool: None
+		}
+	}
+
+	/// Creates request handler which executes batch calls concurrently
+	/// on given number of threads. Outputs keep the order of calls.
+	/// With `0` threads calls are executed on the calling thread, as with `new`.
+	pub fn with_threads(threads: usize) -> Self {
+		RequestHandler {
+			commander: Commander::new(),
+			middlewares: Middlewares(vec![]),
+			pool: match threads {
+				0 => None,
+				threads => Some(ThreadPool::new(threads))
+			}
 		}
 	}
 
@@ -18,56 +117,196 @@ impl RequestHandler {
 		self.commander.add_method(name, command)
 	}
 
+	#[inline]
+	pub fn add_meta_method<C>(&mut self, name: String, command: Box<C>) where C: MetaMethodCommand<M> + 'static {
+		self.commander.add_meta_method(name, command)
+	}
+
 	#[inline]
 	pub fn add_methods(&mut self, methods: HashMap<String, Box<MethodCommand>>) {
 		self.commander.add_methods(methods);
 	}
 
+	#[inline]
+	pub fn add_async_method<C>(&mut self, name: String, command: Box<C>) where C: AsyncMethodCommand + 'static {
+		self.commander.add_async_method(name, command)
+	}
+
+	#[inline]
+	pub fn add_meta_async_method<C>(&mut self, name: String, command: Box<C>) where C: MetaAsyncMethodCommand<M> + 'static {
+		self.commander.add_meta_async_method(name, command)
+	}
+
 	#[inline]
 	pub fn add_notification<C>(&mut self, name: String, command: Box<C>) where C: NotificationCommand + 'static {
 		self.commander.add_notification(name, command)
 	}
 
+	#[inline]
+	pub fn add_meta_notification<C>(&mut self, name: String, command: Box<C>) where C: MetaNotificationCommand<M> + 'static {
+		self.commander.add_meta_notification(name, command)
+	}
+
 	#[inline]
 	pub fn add_notifications(&mut self, notifications: HashMap<String, Box<NotificationCommand>>) {
 		self.commander.add_notifications(notifications);
 	}
 
-	pub fn handle_request(&mut self, request: Request) -> Option<Response> {
-		match request {
-			Request::Single(call) => self.handle_call(call).map(Response::Single),
-			Request::Batch(calls) => {
-				let outs: Vec<Output> = calls.into_iter().filter_map(|call| self.handle_call(call)).collect();
-				match outs.len() {
-					0 => None,
-					_ => Some(Response::Batch(outs))
+	#[inline]
+	pub fn set_service_info(&mut self, service: ServiceInfo) {
+		self.commander.set_service_info(service)
+	}
+
+	#[inline]
+	pub fn describe_method(&mut self, name: String, info: MethodInfo) {
+		self.commander.describe_method(name, info)
+	}
+
+	#[inline]
+	pub fn discover(&self) -> Value {
+		self.commander.discover()
+	}
+
+	/// Adds middleware. Middlewares are called in the order they were added.
+	pub fn add_middleware<W>(&mut self, middleware: W) where W: Middleware<M> + 'static {
+		self.middlewares.0.push(Arc::new(Box::new(middleware) as Box<Middleware<M>>));
+	}
+
+	pub fn handle_request(&self, request: Request, meta: M) -> Option<Response> {
+		let response = match self.middlewares.on_request(request, &meta) {
+			Request::Single(call) => self.handle_call(call, meta).map(Response::Single),
+			Request::Batch(calls) => match self.pool.is_some() {
+				true => {
+					let (tx, rx) = mpsc::channel();
+					self.handle_calls_async(calls, true, meta, move |response| {
+						let _ = tx.send(response);
+					});
+					rx.recv().unwrap_or(None)
+				},
+				false => {
+					let outs: Vec<Output> = calls.into_iter().filter_map(|call| self.handle_call(call, meta.clone())).collect();
+					match outs.len() {
+						0 => None,
+						_ => Some(Response::Batch(outs))
+					}
 				}
 			}
+		};
+
+		self.middlewares.on_response(response)
+	}
+
+	/// Handles request without waiting for asynchronous methods.
+	/// `on_response` is called once every call of the request is done.
+	pub fn handle_request_async<F>(&self, request: Request, meta: M, on_response: F) where F: FnOnce(Option<Response>) + Send + 'static {
+		let middlewares = self.middlewares.clone();
+		let on_response = move |response: Option<Response>| on_response(middlewares.on_response(response));
+		match self.middlewares.on_request(request, &meta) {
+			Request::Single(call) => self.handle_calls_async(vec![call], false, meta, on_response),
+			Request::Batch(calls) => self.handle_calls_async(calls, true, meta, on_response)
 		}
 	}
 
-	fn handle_call(&mut self, call: Call) -> Option<Output> {
+	fn handle_calls_async<F>(&self, calls: Vec<Call>, batch: bool, meta: M, on_response: F) where F: FnOnce(Option<Response>) + Send + 'static {
+		// pending starts from 1, so response is not sent before every call is dispatched
+		let state = Arc::new(Mutex::new(AsyncOutputs {
+			outputs: Vec::with_capacity(calls.len()),
+			pending: 1,
+			batch: batch,
+			on_response: Some(on_response)
+		}));
+
+		for call in calls {
+			let call = match self.middlewares.before(call, &meta) {
+				Ok(call) => call,
+				Err((passed, output)) => {
+					state.lock().unwrap().outputs.push(self.middlewares.after(passed, output));
+					continue;
+				}
+			};
+
+			match call {
+				Call::MethodCall(method) => {
+					let index = {
+						let mut state = state.lock().unwrap();
+						state.outputs.push(None);
+						state.pending += 1;
+						state.outputs.len() - 1
+					};
+
+					let params = match method.params {
+						Some(p) => p,
+						None => Params::None
+					};
+
+					let id = method.id;
+					let state = state.clone();
+					let middlewares = self.middlewares.clone();
+					let ready = Ready::new(move |result| {
+						let output = match result {
+							Ok(result) => Output::Success(Success {
+								id: id.clone(),
+								jsonrpc: Version::V2,
+								result: result
+							}),
+							Err(error) => Output::Failure(Failure {
+								id: id.clone(),
+								jsonrpc: Version::V2,
+								error: error
+							})
+						};
+						let output = middlewares.after(middlewares.len(), Some(output));
+						AsyncOutputs::complete(&state, Some((index, output)));
+					});
+
+					self.dispatch_method(method.method, params, meta.clone(), ready);
+				},
+				call => {
+					let output = self.middlewares.after(self.middlewares.len(), self.execute_call(call, meta.clone()));
+					state.lock().unwrap().outputs.push(output);
+				}
+			}
+		}
+
+		AsyncOutputs::complete(&state, None);
+	}
+
+	/// Executes method on the thread pool if there is one.
+	fn dispatch_method(&self, name: String, params: Params, meta: M, ready: Ready) {
+		if let Some(ref pool) = self.pool {
+			if let Some(command) = self.commander.shared_method(&name) {
+				return pool.execute(move || ready.ready(command.execute(params, meta)));
+			}
+		}
+
+		self.commander.execute_method_async(name, params, meta, ready)
+	}
+
+	fn handle_call(&self, call: Call, meta: M) -> Option<Output> {
+		match self.middlewares.before(call, &meta) {
+			Ok(call) => self.middlewares.after(self.middlewares.len(), self.execute_call(call, meta)),
+			Err((passed, output)) => self.middlewares.after(passed, output)
+		}
+	}
+
+	fn execute_call(&self, call: Call, meta: M) -> Option<Output> {
 		match call {
-			Call::MethodCall(method) => Some(self.handle_method_call(method)),
+			Call::MethodCall(method) => Some(self.handle_method_call(method, meta)),
 			Call::Notification(notification) => {
-				self.handle_notification(notification);
+				self.handle_notification(notification, meta);
 				None
 			},
-			Call::Invalid => Some(Output::Failure(Failure {
-				id: Id::Null,
-				jsonrpc: Version::V2,
-				error: Error::new(ErrorCode::InvalidRequest)
-			}))
+			call @ Call::Invalid(_) => Some(Output::Failure(invalid_call_failure(&call)))
 		}
 	}
 
-	fn handle_method_call(&mut self, method: MethodCall) -> Output {
+	fn handle_method_call(&self, method: MethodCall, meta: M) -> Output {
 		let params = match method.params {
 			Some(p) => p,
 			None => Params::None
 		};
 
-		match self.commander.execute_method(method.method, params) {
+		match self.commander.execute_method(method.method, params, meta) {
 			Ok(result) => Output::Success(Success {
 				id: method.id,
 				jsonrpc: method.jsonrpc,
@@ -81,12 +320,29 @@ impl RequestHandler {
 		}
 	}
 
-	fn handle_notification(&mut self, notification: Notification) {
+	fn handle_notification(&self, notification: Notification, meta: M) {
 		let params = match notification.params {
 			Some(p) => p,
 			None => Params::None
 		};
 
-		self.commander.execute_notification(notification.method, params)
+		self.commander.execute_notification(notification.method, params, meta)
+	}
+}
+
+/// Answers invalid call with its id, if it can be recovered,
+/// and explains what is wrong with it in `data`.
+fn invalid_call_failure(call: &Call) -> Failure {
+	let id = match *call {
+		Call::Invalid(Value::Object(ref object)) => object.get("id").and_then(|id| from_value(id.clone()).ok()),
+		_ => None
+	};
+
+	let mut error = Error::invalid_request();
+	error.data = call.invalid_reason().map(|reason| Value::String(reason.to_owned()));
+	Failure {
+		id: id.unwrap_or(Id::Null),
+		jsonrpc: Version::V2,
+		error: error
 	}
 }
@@ -1,15 +1,17 @@
 //! jsonrpc response
-use serde::{Serialize, Serializer};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de;
+use serde_json::value::from_value;
 use super::{Id, Value, Error, Version};
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Success {
 	pub jsonrpc: Version,
 	pub result: Value,
 	pub id: Id
 }
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Failure {
 	pub jsonrpc: Version,
 	pub error: Error,
@@ -22,6 +24,24 @@ pub enum Output {
 	Failure(Failure)
 }
 
+impl Output {
+	/// Id of the call this output answers.
+	pub fn id(&self) -> &Id {
+		match *self {
+			Output::Success(ref s) => &s.id,
+			Output::Failure(ref f) => &f.id
+		}
+	}
+
+	/// Converts output into method call result.
+	pub fn into_result(self) -> Result<Value, Error> {
+		match self {
+			Output::Success(s) => Ok(s.result),
+			Output::Failure(f) => Err(f.error)
+		}
+	}
+}
+
 impl Serialize for Output {
 	fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error> 
 	where S: Serializer {
@@ -32,6 +52,27 @@ impl Serialize for Output {
 	}
 }
 
+impl Deserialize for Output {
+	fn deserialize<D>(deserializer: &mut D) -> Result<Output, D::Error>
+	where D: Deserializer {
+		Value::deserialize(deserializer).and_then(output_from_value)
+	}
+}
+
+/// Classifies parsed value as a failure, if it has `error`, or a success.
+fn output_from_value<E>(value: Value) -> Result<Output, E> where E: de::Error {
+	let is_failure = match value {
+		Value::Object(ref object) => object.contains_key("error"),
+		_ => false
+	};
+
+	let output = match is_failure {
+		true => from_value(value).map(Output::Failure),
+		false => from_value(value).map(Output::Success)
+	};
+	output.map_err(|e| E::syntax(&format!("{}", e)))
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Response {
 	Single(Output),
@@ -48,3 +89,69 @@ impl Serialize for Response {
 	}
 }
 
+impl Deserialize for Response {
+	fn deserialize<D>(deserializer: &mut D) -> Result<Response, D::Error>
+	where D: Deserializer {
+		match try!(Value::deserialize(deserializer)) {
+			Value::Array(values) => values.into_iter().map(output_from_value).collect::<Result<Vec<_>, _>>().map(Response::Batch),
+			value => output_from_value(value).map(Response::Single)
+		}
+	}
+}
+
+#[test]
+fn response_deserialize_batch() {
+	use serde_json;
+	use super::ErrorCode;
+
+	let s = r#"[{"jsonrpc":"2.0","result":"hello","id":1},{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found.","data":null},"id":"a"}]"#;
+	let deserialized: Response = serde_json::from_str(s).unwrap();
+	assert_eq!(deserialized, Response::Batch(vec![
+		Output::Success(Success {
+			jsonrpc: Version::V2,
+			result: Value::String("hello".to_owned()),
+			id: Id::Num(1)
+		}),
+		Output::Failure(Failure {
+			jsonrpc: Version::V2,
+			error: Error::new(ErrorCode::MethodNotFound),
+			id: Id::Str("a".to_owned())
+		})
+	]));
+}
+
+#[cfg(test)]
+impl super::rng::Rng {
+	pub fn output(&mut self) -> Output {
+		match self.below(2) {
+			0 => Output::Success(Success {
+				jsonrpc: Version::V2,
+				result: self.value(3),
+				id: self.id()
+			}),
+			_ => Output::Failure(Failure {
+				jsonrpc: Version::V2,
+				error: self.error(),
+				id: self.id()
+			})
+		}
+	}
+
+	pub fn response(&mut self) -> Response {
+		match self.below(2) {
+			0 => Response::Single(self.output()),
+			_ => Response::Batch((0..self.below(5)).map(|_| self.output()).collect())
+		}
+	}
+}
+
+#[test]
+fn response_roundtrip() {
+	use super::rng::{Rng, ITERATIONS, assert_roundtrip};
+
+	let mut rng = Rng(0x9e3779b97f4a7c15);
+	for _ in 0..ITERATIONS {
+		assert_roundtrip(rng.output());
+		assert_roundtrip(rng.response());
+	}
+}
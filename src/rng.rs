@@ -0,0 +1,58 @@
+//! Deterministic generator for round-trip tests.
+//!
+//! Every protocol type adds its own generator next to its definition.
+//! Generators produce values in their canonical form, e.g. non-negative
+//! numbers are `U64`, because those are the forms deserialization produces.
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use serde_json;
+use super::Value;
+
+/// Number of generated values checked by every round-trip test.
+pub const ITERATIONS: usize = 1000;
+
+/// Xorshift generator, so failures are reproducible.
+pub struct Rng(pub u64);
+
+impl Rng {
+	pub fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	pub fn below(&mut self, n: u64) -> u64 {
+		self.next() % n
+	}
+
+	pub fn string(&mut self) -> String {
+		let chars = ['a', 'z', '0', ' ', '"', '\\', '/', '\n', '\t', 'é', '€', '-'];
+		let len = self.below(8);
+		(0..len).map(|_| chars[self.below(chars.len() as u64) as usize]).collect()
+	}
+
+	pub fn value(&mut self, depth: usize) -> Value {
+		let kinds = match depth {
+			0 => 6,
+			_ => 8
+		};
+		match self.below(kinds) {
+			0 => Value::Null,
+			1 => Value::Bool(self.below(2) == 0),
+			2 => Value::U64(self.next()),
+			3 => Value::I64(-(self.below(i64::max_value() as u64) as i64) - 1),
+			4 => Value::F64((self.below(2000) as f64 - 1000.0) + 0.5),
+			5 => Value::String(self.string()),
+			6 => Value::Array((0..self.below(4)).map(|_| self.value(depth - 1)).collect()),
+			_ => Value::Object((0..self.below(4)).map(|_| (self.string(), self.value(depth - 1))).collect::<BTreeMap<_, _>>())
+		}
+	}
+}
+
+/// Serializes and deserializes value and compares it with the original.
+pub fn assert_roundtrip<T>(value: T) where T: Serialize + Deserialize + PartialEq + ::std::fmt::Debug {
+	let serialized = serde_json::to_string(&value).unwrap();
+	let deserialized: T = serde_json::from_str(&serialized).unwrap_or_else(|e| panic!("{}: {}", serialized, e));
+	assert_eq!(deserialized, value);
+}
@@ -0,0 +1,165 @@
+//! in-memory jsonrpc transport
+//!
+//! Connected endpoints exchange serialized messages over channels,
+//! so requests go through the same serialization path as with sockets.
+//!
+//! ```rust
+//! extern crate jsonrpc_core;
+//! use std::sync::Arc;
+//! use jsonrpc_core::*;
+//! use jsonrpc_core::transports::channel::{spawn, Client};
+//!
+//! fn main() {
+//! 	let mut io = IoHandler::new();
+//! 	io.add_method("say_hello", |_params: Params| -> Result<Value, Error> {
+//! 		Ok(Value::String("hello".to_owned()))
+//! 	});
+//!
+//! 	let mut client = Client::new(spawn(Arc::new(io.into()), |_| ()));
+//! 	assert_eq!(client.call("say_hello", Params::None).unwrap(), Ok(Value::String("hello".to_owned())));
+//! }
+//! ```
+use std::{io, thread};
+use std::sync::{Arc, mpsc};
+use super::handle_message;
+use super::super::{MetaIoHandler, Metadata, Params, Value, Error, Request, Call, Notification};
+use super::super::client::{RequestManager, write_request};
+use super::super::pubsub::Session;
+
+/// One end of in-memory connection.
+pub struct Endpoint {
+	sender: mpsc::Sender<String>,
+	receiver: mpsc::Receiver<String>
+}
+
+impl Endpoint {
+	/// Sends message to the other end.
+	pub fn send(&self, message: String) -> io::Result<()> {
+		self.sender.send(message).map_err(|_| disconnected())
+	}
+
+	/// Waits for next message. Returns `None` once the other end is dropped.
+	pub fn recv(&self) -> Option<String> {
+		self.receiver.recv().ok()
+	}
+
+	/// Returns next message if there is one.
+	pub fn try_recv(&self) -> Option<String> {
+		self.receiver.try_recv().ok()
+	}
+}
+
+fn disconnected() -> io::Error {
+	io::Error::new(io::ErrorKind::BrokenPipe, "endpoint disconnected")
+}
+
+/// Creates pair of connected endpoints.
+pub fn pair() -> (Endpoint, Endpoint) {
+	let (a_tx, a_rx) = mpsc::channel();
+	let (b_tx, b_rx) = mpsc::channel();
+	(Endpoint { sender: a_tx, receiver: b_rx }, Endpoint { sender: b_tx, receiver: a_rx })
+}
+
+/// Serves messages received by `endpoint` until the other end is dropped.
+///
+/// Endpoint has its own `Session`, which is passed to `meta`
+/// to create metadata of the connection. The session is closed
+/// once the other end is dropped.
+pub fn serve<M, F>(handler: &MetaIoHandler<M>, endpoint: Endpoint, meta: F) where
+	M: Metadata,
+	F: FnOnce(Arc<Session>) -> M {
+	let session = Arc::new(Session::new(endpoint.sender.clone()));
+	let meta = meta(session.clone());
+	while let Some(message) = endpoint.recv() {
+		if let Some(response) = handler.handle_request_with_meta(&message, meta.clone()) {
+			// fails only if the other end is already dropped
+			let _ = endpoint.send(response);
+		}
+	}
+	session.close();
+}
+
+/// Serves new connection on a separate thread and returns its client endpoint.
+pub fn spawn<M, F>(handler: Arc<MetaIoHandler<M>>, meta: F) -> Endpoint where
+	M: Metadata,
+	F: FnOnce(Arc<Session>) -> M + Send + 'static {
+	let (client, server) = pair();
+	thread::spawn(move || serve(&handler, server, meta));
+	client
+}
+
+/// Blocking client of in-memory connection.
+///
+/// Notifications received while waiting for a response are kept
+/// until they are taken with `take_notifications`.
+pub struct Client {
+	endpoint: Endpoint,
+	manager: RequestManager<()>,
+	notifications: Vec<Notification>
+}
+
+impl Client {
+	pub fn new(endpoint: Endpoint) -> Self {
+		Client {
+			endpoint: endpoint,
+			manager: RequestManager::new(),
+			notifications: Vec::new()
+		}
+	}
+
+	/// Calls method and waits for its result.
+	pub fn call(&mut self, method: &str, params: Params) -> io::Result<Result<Value, Error>> {
+		let call = self.manager.method_call(method, params, ());
+		let id = call.id.clone();
+		try!(self.endpoint.send(write_request(&Request::Single(Call::MethodCall(call)))));
+
+		loop {
+			let message = try!(self.endpoint.recv().ok_or_else(disconnected));
+			if let Some(result) = handle_message(&mut self.manager, &mut self.notifications, Some(&id), &message) {
+				return Ok(result);
+			}
+		}
+	}
+
+	/// Sends notification.
+	pub fn notify(&mut self, method: &str, params: Params) -> io::Result<()> {
+		self.endpoint.send(write_request(&Request::Single(Call::Notification(Notification::new(method, params)))))
+	}
+
+	/// Returns notifications received so far, without waiting for more.
+	pub fn take_notifications(&mut self) -> Vec<Notification> {
+		while let Some(message) = self.endpoint.try_recv() {
+			handle_message(&mut self.manager, &mut self.notifications, None, &message);
+		}
+		self.notifications.drain(..).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use super::*;
+	use super::super::super::*;
+	use super::super::super::pubsub::Sink;
+
+	#[test]
+	fn test_channel_subscription() {
+		let mut io = MetaIoHandler::<Option<Arc<pubsub::Session>>>::new();
+		io.add_subscription("hello", "subscribe_hello", "unsubscribe_hello", |_params: Params, sink: Sink| -> Result<(), Error> {
+			let _ = sink.notify(Value::String("hello".to_owned()));
+			Ok(())
+		}, |_id: Id| {});
+
+		let mut client = Client::new(spawn(Arc::new(io), Some));
+		let id = client.call("subscribe_hello", Params::None).unwrap().unwrap();
+		assert_eq!(client.call("say_hello", Params::None).unwrap(), Err(Error::method_not_found()));
+
+		let notifications = client.take_notifications();
+		assert_eq!(notifications.len(), 1);
+		assert_eq!(notifications[0].method, "hello".to_owned());
+		assert_eq!(notifications[0].params, Some(Params::Map(vec![
+			("result".to_owned(), Value::String("hello".to_owned())),
+			("subscription".to_owned(), id)
+		].into_iter().collect())));
+	}
+}
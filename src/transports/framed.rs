@@ -0,0 +1,267 @@
+//! `Content-Length` framed jsonrpc transport
+//!
+//! Every message is preceded by headers, as used by the Language Server Protocol:
+//!
+//! ```text
+//! Content-Length: 44\r\n
+//! \r\n
+//! {"jsonrpc":"2.0","method":"exit","params":[]}
+//! ```
+use std::{cmp, io, str, thread};
+use std::io::{Read, Write};
+use std::sync::mpsc;
+use super::parse_error_response;
+use super::super::{MetaIoHandler, Metadata};
+
+/// Headers longer than this are rejected.
+const MAX_HEADERS_LEN: usize = 8 * 1024;
+/// Bodies longer than this are skipped without buffering.
+const MAX_BODY_LEN: usize = 16 * 1024 * 1024;
+const HEADERS_END: &'static [u8] = b"\r\n\r\n";
+
+/// Errors which may occur while decoding a frame.
+#[derive(Debug, PartialEq)]
+pub enum FrameError {
+	/// Headers are malformed or too long.
+	InvalidHeader,
+	/// There is no `Content-Length` header.
+	MissingContentLength,
+	/// Message body is not valid UTF-8.
+	InvalidBody,
+	/// `Content-Length` exceeds the maximum body length.
+	TooLarge
+}
+
+/// Splits byte stream into message bodies.
+pub struct FrameDecoder {
+	buffer: Vec<u8>,
+	/// Bytes of a too large body which are yet to be dropped.
+	skip: usize
+}
+
+impl FrameDecoder {
+	pub fn new() -> Self {
+		FrameDecoder {
+			buffer: Vec::new(),
+			skip: 0
+		}
+	}
+
+	/// Appends bytes read from the stream.
+	pub fn feed(&mut self, data: &[u8]) {
+		let skipped = cmp::min(self.skip, data.len());
+		self.skip -= skipped;
+		self.buffer.extend(data[skipped..].iter().cloned());
+	}
+
+	/// Returns next complete message body, or `None` if more data is needed.
+	///
+	/// Malformed headers and too large bodies are dropped,
+	/// so decoding can continue after an error.
+	pub fn next_frame(&mut self) -> Result<Option<String>, FrameError> {
+		let headers_len = match self.buffer.windows(HEADERS_END.len()).position(|w| w == HEADERS_END) {
+			Some(pos) => pos,
+			None if self.buffer.len() > MAX_HEADERS_LEN => {
+				self.buffer.clear();
+				return Err(FrameError::InvalidHeader);
+			},
+			None => return Ok(None)
+		};
+		let body_start = headers_len + HEADERS_END.len();
+
+		let content_length = match parse_content_length(&self.buffer[..headers_len]) {
+			Ok(len) => len,
+			Err(err) => {
+				self.buffer.drain(..body_start);
+				return Err(err);
+			}
+		};
+
+		let body_end = match body_start.checked_add(content_length) {
+			Some(end) if content_length <= MAX_BODY_LEN => end,
+			_ => {
+				self.buffer.drain(..body_start);
+				let buffered = cmp::min(self.buffer.len(), content_length);
+				self.buffer.drain(..buffered);
+				self.skip = content_length - buffered;
+				return Err(FrameError::TooLarge);
+			}
+		};
+
+		if self.buffer.len() < body_end {
+			return Ok(None);
+		}
+
+		let body: Vec<u8> = self.buffer.drain(..body_end).skip(body_start).collect();
+		String::from_utf8(body).map(Some).map_err(|_| FrameError::InvalidBody)
+	}
+}
+
+fn parse_content_length(headers: &[u8]) -> Result<usize, FrameError> {
+	let headers = try!(str::from_utf8(headers).map_err(|_| FrameError::InvalidHeader));
+	let mut content_length = None;
+
+	for header in headers.split("\r\n") {
+		let mut parts = header.splitn(2, ':');
+		let name = parts.next().unwrap_or("").trim();
+		let value = try!(parts.next().ok_or(FrameError::InvalidHeader)).trim();
+
+		if name.eq_ignore_ascii_case("content-length") {
+			content_length = Some(try!(value.parse::<usize>().map_err(|_| FrameError::InvalidHeader)));
+		}
+	}
+
+	content_length.ok_or(FrameError::MissingContentLength)
+}
+
+/// Prepends `Content-Length` header to message body.
+pub fn encode_frame(body: &str) -> Vec<u8> {
+	let mut frame = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+	frame.extend(body.as_bytes().iter().cloned());
+	frame
+}
+
+/// Handles every message read from `reader` with default metadata
+/// until end of input.
+pub fn serve<M, R, W>(handler: &MetaIoHandler<M>, reader: R, writer: W) -> io::Result<()> where
+	M: Metadata,
+	R: Read,
+	W: Write + Send + 'static {
+	serve_with_meta(handler, reader, writer, M::default())
+}
+
+/// Handles every message read from `reader` with given metadata
+/// until end of input. Malformed messages are answered with `ParseError`.
+///
+/// Responses are written by a separate thread as soon as they are ready,
+/// so asynchronous methods don't stop reading. Returns once every response is written.
+pub fn serve_with_meta<M, R, W>(handler: &MetaIoHandler<M>, mut reader: R, writer: W, meta: M) -> io::Result<()> where
+	M: Metadata,
+	R: Read,
+	W: Write + Send + 'static {
+	let (tx, rx) = mpsc::channel::<String>();
+	let writer_thread = thread::spawn(move || -> io::Result<()> {
+		let mut writer = writer;
+		for response in rx.iter() {
+			try!(writer.write_all(&encode_frame(&response)));
+			try!(writer.flush());
+		}
+		Ok(())
+	});
+
+	let result = read_frames(handler, &mut reader, &tx, &meta);
+
+	// writer finishes once every asynchronous response is sent
+	drop(tx);
+	let written = writer_thread.join().unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "writer panicked")));
+	result.and(written)
+}
+
+fn read_frames<M, R>(handler: &MetaIoHandler<M>, reader: &mut R, tx: &mpsc::Sender<String>, meta: &M) -> io::Result<()> where
+	M: Metadata,
+	R: Read {
+	let mut decoder = FrameDecoder::new();
+	let mut buf = [0u8; 4096];
+
+	loop {
+		loop {
+			let tx = tx.clone();
+			match decoder.next_frame() {
+				Ok(Some(body)) => handler.handle_request_async_with_meta(&body, meta.clone(), move |response| {
+					if let Some(response) = response {
+						// fails only if writing already failed
+						let _ = tx.send(response);
+					}
+				}),
+				Ok(None) => break,
+				Err(_) => {
+					let _ = tx.send(parse_error_response());
+				}
+			}
+		}
+
+		match reader.read(&mut buf) {
+			Ok(0) => return Ok(()),
+			Ok(read) => decoder.feed(&buf[..read]),
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use std::thread;
+	use super::*;
+	use super::super::SharedBuffer;
+	use super::super::super::*;
+
+	#[test]
+	fn test_partial_and_multiple_frames() {
+		let mut decoder = FrameDecoder::new();
+		decoder.feed(b"Content-Len");
+		assert_eq!(decoder.next_frame(), Ok(None));
+		decoder.feed(b"gth: 2\r\n\r\n{");
+		assert_eq!(decoder.next_frame(), Ok(None));
+		decoder.feed(b"}Content-Type: application/json\r\ncontent-length: 4\r\n\r\n[1]\n");
+		assert_eq!(decoder.next_frame(), Ok(Some("{}".to_owned())));
+		assert_eq!(decoder.next_frame(), Ok(Some("[1]\n".to_owned())));
+		assert_eq!(decoder.next_frame(), Ok(None));
+	}
+
+	#[test]
+	fn test_header_errors() {
+		let mut decoder = FrameDecoder::new();
+		decoder.feed(b"Content-Length: x\r\n\r\nContent-Type: a\r\n\r\nbroken\r\n\r\nContent-Length: 2\r\n\r\n{}");
+		assert_eq!(decoder.next_frame(), Err(FrameError::InvalidHeader));
+		assert_eq!(decoder.next_frame(), Err(FrameError::MissingContentLength));
+		assert_eq!(decoder.next_frame(), Err(FrameError::InvalidHeader));
+		assert_eq!(decoder.next_frame(), Ok(Some("{}".to_owned())));
+	}
+
+	#[test]
+	fn test_too_large_body() {
+		use std::usize;
+		use super::MAX_BODY_LEN;
+
+		let mut decoder = FrameDecoder::new();
+		decoder.feed(format!("Content-Length: {}\r\n\r\n{{", MAX_BODY_LEN + 1).as_bytes());
+		assert_eq!(decoder.next_frame(), Err(FrameError::TooLarge));
+		decoder.feed(&vec![b' '; MAX_BODY_LEN]);
+		decoder.feed(b"Content-Length: 2\r\n\r\n{}");
+		assert_eq!(decoder.next_frame(), Ok(Some("{}".to_owned())));
+
+		// length which overflows the body end
+		let mut decoder = FrameDecoder::new();
+		decoder.feed(format!("Content-Length: {}\r\n\r\n{{}}", usize::MAX).as_bytes());
+		assert_eq!(decoder.next_frame(), Err(FrameError::TooLarge));
+		assert_eq!(decoder.next_frame(), Ok(None));
+	}
+
+	#[test]
+	fn test_serve() {
+		let mut handler = IoHandler::new();
+		handler.add_method("say_hello", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("hello".to_owned()))
+		});
+		handler.add_async_method("say_later", |_params: Params, ready: Ready| {
+			thread::spawn(move || ready.ready(Ok(Value::String("later".to_owned()))));
+		});
+
+		let mut input = encode_frame(r#"{"jsonrpc": "2.0", "method": "say_hello", "id": 1}"#);
+		input.extend(encode_frame("{").into_iter());
+		let output = SharedBuffer::new();
+		serve(&handler, Cursor::new(input), output.clone()).unwrap();
+
+		let mut expected = encode_frame(r#"{"jsonrpc":"2.0","result":"hello","id":1}"#);
+		expected.extend(encode_frame(r#"{"jsonrpc":"2.0","error":{"code":-32700,"message":"Parse error.","data":null},"id":null}"#).into_iter());
+		assert_eq!(output.contents(), expected);
+
+		// serving ends once asynchronous response is written
+		let input = encode_frame(r#"{"jsonrpc": "2.0", "method": "say_later", "id": 2}"#);
+		let output = SharedBuffer::new();
+		serve(&handler, Cursor::new(input), output.clone()).unwrap();
+		assert_eq!(output.contents(), encode_frame(r#"{"jsonrpc":"2.0","result":"later","id":2}"#));
+	}
+}
@@ -0,0 +1,233 @@
+//! minimal HTTP/1.1 jsonrpc server
+//!
+//! Every connection carries a single `POST` request with
+//! `Content-Type: application/json` and is closed after the response.
+//! Requests made only of notifications are answered with `204 No Content`.
+use std::io;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, SocketAddr};
+use std::sync::Arc;
+use super::{Listening, read_head, Head, MAX_HEADERS_LEN, HEADERS_END};
+use super::super::{MetaIoHandler, Metadata};
+
+/// Http server options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+	/// Requests with larger body are rejected with `413 Payload Too Large`.
+	pub max_body_size: usize,
+	/// Origins allowed to make cross-origin requests. `"*"` allows any origin.
+	pub allowed_origins: Vec<String>
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Options {
+			max_body_size: 5 * 1024 * 1024,
+			allowed_origins: vec![]
+		}
+	}
+}
+
+/// Http server.
+///
+/// ```rust
+/// extern crate jsonrpc_core;
+/// use std::sync::Arc;
+/// use std::io::{Read, Write};
+/// use std::net::TcpStream;
+/// use jsonrpc_core::*;
+/// use jsonrpc_core::transports::http::{Server, Options};
+///
+/// fn main() {
+/// 	let mut io = IoHandler::new();
+/// 	io.add_method("say_hello", |_params: Params| -> Result<Value, Error> {
+/// 		Ok(Value::String("hello".to_owned()))
+/// 	});
+///
+/// 	let server = Server::start(&"127.0.0.1:0".parse().unwrap(), Options::default(), Arc::new(io.into()), |_| ()).unwrap();
+///
+/// 	let body = r#"{"jsonrpc": "2.0", "method": "say_hello", "id": 1}"#;
+/// 	let mut client = TcpStream::connect(server.local_addr()).unwrap();
+/// 	write!(client, "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+///
+/// 	let mut response = String::new();
+/// 	client.read_to_string(&mut response).unwrap();
+/// 	assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+/// 	assert!(response.ends_with("\r\n\r\n{\"jsonrpc\":\"2.0\",\"result\":\"hello\",\"id\":1}"));
+///
+/// 	server.close();
+/// }
+/// ```
+pub struct Server {
+	local_addr: SocketAddr,
+	listening: Listening
+}
+
+impl Server {
+	/// Starts listening on given address.
+	///
+	/// `meta` creates metadata of every request from its peer address.
+	pub fn start<M, F>(addr: &SocketAddr, options: Options, handler: Arc<MetaIoHandler<M>>, meta: F) -> io::Result<Self> where
+		M: Metadata,
+		F: Fn(&SocketAddr) -> M + Send + Sync + 'static {
+		let listener = try!(TcpListener::bind(addr));
+		let local_addr = try!(listener.local_addr());
+		let listening = try!(Listening::start(listener, move |stream: TcpStream, writer| {
+			let peer = match stream.peer_addr() {
+				Ok(peer) => peer,
+				Err(_) => return
+			};
+			let _ = serve_request(&handler, &options, stream, writer, || (*meta)(&peer));
+		}));
+
+		Ok(Server {
+			local_addr: local_addr,
+			listening: listening
+		})
+	}
+
+	/// Address the server is listening on.
+	pub fn local_addr(&self) -> &SocketAddr {
+		&self.local_addr
+	}
+
+	/// Stops accepting connections and waits until every connection is closed.
+	pub fn close(mut self) {
+		self.listening.stop()
+	}
+}
+
+/// Reads single request from `reader` and writes its response to `writer`.
+///
+/// Response of asynchronous methods is written once they are done,
+/// without holding the connection thread.
+fn serve_request<M, R, W, F>(handler: &MetaIoHandler<M>, options: &Options, mut reader: R, mut writer: W, meta: F) -> io::Result<()> where
+	M: Metadata,
+	R: Read,
+	W: Write + Send + 'static,
+	F: FnOnce() -> M {
+	let mut buffer = vec![];
+	let head_len = match try!(read_head(&mut reader, &mut buffer)) {
+		Some(len) => len,
+		None if buffer.len() > MAX_HEADERS_LEN => return write_response(&mut writer, "431 Request Header Fields Too Large", &[], ""),
+		None => return Ok(())
+	};
+
+	let head = match Head::parse(&buffer[..head_len]) {
+		Some(head) => head,
+		None => return write_response(&mut writer, "400 Bad Request", &[], "")
+	};
+
+	let mut headers = vec![];
+	if let Some(origin) = head.header("origin") {
+		if options.allowed_origins.iter().any(|allowed| allowed == "*" || allowed == origin) {
+			headers.push(("Access-Control-Allow-Origin", origin.to_owned()));
+		}
+	}
+
+	match &head.method as &str {
+		"POST" => {},
+		"OPTIONS" => {
+			headers.push(("Access-Control-Allow-Methods", "POST, OPTIONS".to_owned()));
+			headers.push(("Access-Control-Allow-Headers", "Content-Type".to_owned()));
+			headers.push(("Allow", "POST, OPTIONS".to_owned()));
+			return write_response(&mut writer, "200 OK", &headers, "");
+		},
+		_ => {
+			headers.push(("Allow", "POST, OPTIONS".to_owned()));
+			return write_response(&mut writer, "405 Method Not Allowed", &headers, "");
+		}
+	}
+
+	let is_json = head.header("content-type")
+		.map(|value| value.split(';').next().unwrap_or("").trim().eq_ignore_ascii_case("application/json"))
+		.unwrap_or(false);
+	if !is_json {
+		return write_response(&mut writer, "415 Unsupported Media Type", &headers, "");
+	}
+
+	let content_length = match head.header("content-length").map(|value| value.parse::<usize>()) {
+		Some(Ok(len)) => len,
+		Some(Err(_)) => return write_response(&mut writer, "400 Bad Request", &headers, ""),
+		None => return write_response(&mut writer, "411 Length Required", &headers, "")
+	};
+	if content_length > options.max_body_size {
+		return write_response(&mut writer, "413 Payload Too Large", &headers, "");
+	}
+
+	let mut body: Vec<u8> = buffer.drain(head_len + HEADERS_END.len()..).take(content_length).collect();
+	let missing = content_length - body.len();
+	try!(reader.take(missing as u64).read_to_end(&mut body));
+	if body.len() < content_length {
+		return Ok(());
+	}
+
+	let body = String::from_utf8_lossy(&body);
+	handler.handle_request_async_with_meta(&body, meta(), move |response| {
+		// fails only if the client is already gone
+		let _ = match response {
+			Some(response) => {
+				headers.push(("Content-Type", "application/json".to_owned()));
+				write_response(&mut writer, "200 OK", &headers, &response)
+			},
+			None => write_response(&mut writer, "204 No Content", &headers, "")
+		};
+	});
+	Ok(())
+}
+
+fn write_response<W>(writer: &mut W, status: &str, headers: &[(&str, String)], body: &str) -> io::Result<()> where W: Write {
+	let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+	if !status.starts_with("204") {
+		response.push_str(&format!("Content-Length: {}\r\n", body.len()));
+	}
+	for &(name, ref value) in headers {
+		response.push_str(&format!("{}: {}\r\n", name, value));
+	}
+	response.push_str("\r\n");
+	response.push_str(body);
+
+	try!(writer.write_all(response.as_bytes()));
+	writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use super::*;
+	use super::super::SharedBuffer;
+	use super::super::super::*;
+
+	fn request(io: &IoHandler, options: &Options, request: &str) -> String {
+		let output = SharedBuffer::new();
+		serve_request(io, options, Cursor::new(request.as_bytes()), output.clone(), || ()).unwrap();
+		String::from_utf8(output.contents()).unwrap()
+	}
+
+	#[test]
+	fn test_http_requests() {
+		let mut io = IoHandler::new();
+		io.add_method("say_hello", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("hello".to_owned()))
+		});
+		let options = Options {
+			max_body_size: 100,
+			allowed_origins: vec!["http://example.com".to_owned()]
+		};
+
+		let body = r#"{"jsonrpc": "2.0", "method": "say_hello", "id": 1}"#;
+		assert_eq!(request(&io, &options, &format!("POST / HTTP/1.1\r\nOrigin: http://example.com\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\n\r\n{}", body.len(), body)),
+			"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 41\r\nAccess-Control-Allow-Origin: http://example.com\r\nContent-Type: application/json\r\n\r\n{\"jsonrpc\":\"2.0\",\"result\":\"hello\",\"id\":1}");
+
+		let body = r#"{"jsonrpc": "2.0", "method": "say_hello"}"#;
+		assert_eq!(request(&io, &options, &format!("POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body)),
+			"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n");
+
+		assert_eq!(request(&io, &options, "OPTIONS / HTTP/1.1\r\nOrigin: http://other.com\r\n\r\n"),
+			"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\nAccess-Control-Allow-Methods: POST, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type\r\nAllow: POST, OPTIONS\r\n\r\n");
+
+		assert!(request(&io, &options, "GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 405 "));
+		assert!(request(&io, &options, "POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n{}").starts_with("HTTP/1.1 415 "));
+		assert!(request(&io, &options, "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 101\r\n\r\n").starts_with("HTTP/1.1 413 "));
+	}
+}
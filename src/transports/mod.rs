@@ -0,0 +1,319 @@
+//! ready-made jsonrpc transports
+use std::{io, str, thread};
+use std::io::{Read, Write};
+use std::collections::HashMap;
+use std::net::{TcpListener, TcpStream, Shutdown};
+use std::sync::{Arc, Mutex, mpsc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use serde_json;
+use super::{Response, Output, Failure, Id, Version, Error, Value, Notification};
+use super::client::{RequestManager, read_response};
+
+pub mod stdio;
+pub mod framed;
+pub mod stream;
+pub mod tcp;
+pub mod http;
+pub mod ws;
+pub mod channel;
+pub mod peer;
+#[cfg(unix)]
+pub mod unix;
+
+/// How often a listener checks whether it should stop accepting.
+const ACCEPT_INTERVAL_MS: u64 = 50;
+
+/// Http request headers longer than this are rejected.
+const MAX_HEADERS_LEN: usize = 8 * 1024;
+const HEADERS_END: &'static [u8] = b"\r\n\r\n";
+
+/// Serialized `ParseError` failure, sent back when message can't be read.
+fn parse_error_response() -> String {
+	let response = Response::Single(Output::Failure(Failure {
+		id: Id::Null,
+		jsonrpc: Version::V2,
+		error: Error::parse_error()
+	}));
+	// this should never fail
+	serde_json::to_string(&response).unwrap()
+}
+
+/// Handles message received by a client while it waits for response
+/// to the call with `awaited` id, if there is any.
+///
+/// Returns result of the call once its response is received.
+/// Notifications are stored, responses to other calls are ignored.
+fn handle_message(manager: &mut RequestManager<()>, notifications: &mut Vec<Notification>, awaited: Option<&Id>, message: &str) -> Option<Result<Value, Error>> {
+	match read_response(message) {
+		Ok(response) => {
+			let results = match awaited {
+				Some(id) => manager.handle_call_response(id, response),
+				None => manager.handle_response(response)
+			};
+			results.into_iter().filter_map(|result| result.ok()).map(|(_, result)| result).next()
+		},
+		Err(_) => {
+			if let Ok(notification) = serde_json::from_str(message) {
+				notifications.push(notification);
+			}
+			None
+		}
+	}
+}
+
+/// Accepted stream connection.
+trait Connection: Read + Write + Send + Sized + 'static {
+	fn try_clone(&self) -> io::Result<Self>;
+
+	fn shutdown_read(&self) -> io::Result<()>;
+
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl Connection for TcpStream {
+	fn try_clone(&self) -> io::Result<Self> {
+		TcpStream::try_clone(self)
+	}
+
+	fn shutdown_read(&self) -> io::Result<()> {
+		self.shutdown(Shutdown::Read)
+	}
+
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+		TcpStream::set_nonblocking(self, nonblocking)
+	}
+}
+
+#[cfg(unix)]
+impl Connection for ::std::os::unix::net::UnixStream {
+	fn try_clone(&self) -> io::Result<Self> {
+		::std::os::unix::net::UnixStream::try_clone(self)
+	}
+
+	fn shutdown_read(&self) -> io::Result<()> {
+		self.shutdown(Shutdown::Read)
+	}
+
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+		::std::os::unix::net::UnixStream::set_nonblocking(self, nonblocking)
+	}
+}
+
+/// Listener of stream connections.
+trait Listener: Send + 'static {
+	type Connection: Connection;
+
+	fn accept_connection(&self) -> io::Result<Self::Connection>;
+
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl Listener for TcpListener {
+	type Connection = TcpStream;
+
+	fn accept_connection(&self) -> io::Result<TcpStream> {
+		self.accept().map(|(stream, _)| stream)
+	}
+
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+		TcpListener::set_nonblocking(self, nonblocking)
+	}
+}
+
+#[cfg(unix)]
+impl Listener for ::std::os::unix::net::UnixListener {
+	type Connection = ::std::os::unix::net::UnixStream;
+
+	fn accept_connection(&self) -> io::Result<Self::Connection> {
+		self.accept().map(|(stream, _)| stream)
+	}
+
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+		::std::os::unix::net::UnixListener::set_nonblocking(self, nonblocking)
+	}
+}
+
+/// Serves every incoming connection on its own thread until `running` is unset.
+///
+/// Listener doesn't block, so stopping it never depends on a connection
+/// waking it up. Then stops reading from open connections and waits
+/// until all of them are closed.
+fn accept_connections<L, F>(listener: L, running: &AtomicBool, serve: F) where
+	L: Listener,
+	F: Fn(L::Connection, L::Connection) + Send + Sync + 'static {
+	let serve = Arc::new(serve);
+	let connections = Arc::new(Mutex::new(HashMap::new()));
+	// every connection holds a sender, so receiver knows when all of them are closed
+	let (done_tx, done_rx) = mpsc::channel::<()>();
+	let mut next_id = 0usize;
+
+	while running.load(Ordering::SeqCst) {
+		let stream = match listener.accept_connection() {
+			Ok(stream) => stream,
+			// nothing to accept, or a temporary failure like too many open files
+			Err(_) => {
+				thread::sleep(Duration::from_millis(ACCEPT_INTERVAL_MS));
+				continue;
+			}
+		};
+
+		// accepted stream may inherit non-blocking mode of the listener
+		if stream.set_nonblocking(false).is_err() {
+			continue;
+		}
+
+		let id = next_id;
+		next_id += 1;
+
+		let (writer, closer) = match (stream.try_clone(), stream.try_clone()) {
+			(Ok(writer), Ok(closer)) => (writer, closer),
+			_ => continue
+		};
+
+		connections.lock().unwrap().insert(id, closer);
+		let serve = serve.clone();
+		let connections = connections.clone();
+		let done_tx = done_tx.clone();
+		thread::spawn(move || {
+			(*serve)(stream, writer);
+			connections.lock().unwrap().remove(&id);
+			drop(done_tx);
+		});
+	}
+
+	// stop reading, so every connection finishes after answering received requests
+	for (_, stream) in connections.lock().unwrap().iter() {
+		let _ = stream.shutdown_read();
+	}
+	drop(done_tx);
+	let _ = done_rx.recv();
+}
+
+/// Listener accepting connections on a background thread until it's stopped.
+///
+/// Shared by the servers, which only provide the way every connection is served.
+struct Listening {
+	running: Arc<AtomicBool>,
+	thread: Option<thread::JoinHandle<()>>
+}
+
+impl Listening {
+	/// Starts accepting connections, each of them is passed to `serve`
+	/// together with its writing half.
+	fn start<L, F>(listener: L, serve: F) -> io::Result<Self> where
+		L: Listener,
+		F: Fn(L::Connection, L::Connection) + Send + Sync + 'static {
+		try!(listener.set_nonblocking(true));
+		let running = Arc::new(AtomicBool::new(true));
+
+		let is_running = running.clone();
+		let thread = thread::spawn(move || {
+			accept_connections(listener, &is_running, serve);
+		});
+
+		Ok(Listening {
+			running: running,
+			thread: Some(thread)
+		})
+	}
+
+	/// Stops accepting connections and waits until every connection is closed.
+	fn stop(&mut self) {
+		if let Some(thread) = self.thread.take() {
+			self.running.store(false, Ordering::SeqCst);
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for Listening {
+	fn drop(&mut self) {
+		self.stop()
+	}
+}
+
+/// Writer whose output can be checked by the test after it's moved to another thread.
+#[cfg(test)]
+#[derive(Clone)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl SharedBuffer {
+	fn new() -> Self {
+		SharedBuffer(Arc::new(Mutex::new(Vec::new())))
+	}
+
+	fn contents(&self) -> Vec<u8> {
+		self.0.lock().unwrap().clone()
+	}
+}
+
+#[cfg(test)]
+impl Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.lock().unwrap().write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Request line and headers of http request.
+struct Head {
+	method: String,
+	headers: Vec<(String, String)>
+}
+
+impl Head {
+	fn parse(head: &[u8]) -> Option<Head> {
+		let head = match str::from_utf8(head) {
+			Ok(head) => head,
+			Err(_) => return None
+		};
+		let mut lines = head.split("\r\n");
+		let method = match lines.next().and_then(|line| line.split(' ').next()) {
+			Some(method) if !method.is_empty() => method.to_owned(),
+			_ => return None
+		};
+
+		let mut headers = vec![];
+		for line in lines {
+			let mut parts = line.splitn(2, ':');
+			match (parts.next(), parts.next()) {
+				(Some(name), Some(value)) => headers.push((name.trim().to_owned(), value.trim().to_owned())),
+				_ => return None
+			}
+		}
+
+		Some(Head {
+			method: method,
+			headers: headers
+		})
+	}
+
+	fn header(&self, name: &str) -> Option<&str> {
+		self.headers.iter().find(|h| h.0.eq_ignore_ascii_case(name)).map(|h| &h.1 as &str)
+	}
+}
+
+/// Reads until the end of headers and returns their length,
+/// or `None` if connection was closed or headers are too long.
+fn read_head<R>(reader: &mut R, buffer: &mut Vec<u8>) -> io::Result<Option<usize>> where R: Read {
+	let mut buf = [0u8; 4096];
+	loop {
+		if let Some(pos) = buffer.windows(HEADERS_END.len()).position(|w| w == HEADERS_END) {
+			return Ok(Some(pos));
+		}
+		if buffer.len() > MAX_HEADERS_LEN {
+			return Ok(None);
+		}
+		match reader.read(&mut buf) {
+			Ok(0) => return Ok(None),
+			Ok(read) => buffer.extend(buf[..read].iter().cloned()),
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e)
+		}
+	}
+}
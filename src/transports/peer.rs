@@ -0,0 +1,294 @@
+//! bidirectional jsonrpc peer
+//!
+//! Both sides of a connection may serve requests and make calls.
+//! Incoming messages with `result` or `error` and without `method`
+//! are responses to our calls, everything else is handled as a request.
+use std::{io, thread};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, mpsc};
+use serde_json;
+use serde_json::value::from_value;
+use super::parse_error_response;
+use super::framed::{FrameDecoder, encode_frame};
+use super::stream::JsonSplitter;
+use super::super::{MetaIoHandler, Metadata, Params, Value, Error, Request, Response, Call, Notification};
+use super::super::client::{RequestManager, write_request};
+use super::super::pubsub::Session;
+
+/// How messages are separated in the stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framing {
+	/// Messages are separated by newlines or simply concatenated and must not span lines.
+	Lines,
+	/// Every message is preceded by `Content-Length` header.
+	ContentLength
+}
+
+type Pending = Arc<Mutex<Option<RequestManager<mpsc::Sender<Result<Value, Error>>>>>>;
+
+/// Serves incoming requests and makes outgoing calls over one duplex stream.
+///
+/// Every request is handled on its own thread, so its handler may make calls
+/// to the other side, even if they lead to calls back to this side.
+pub struct Peer {
+	sender: Mutex<mpsc::Sender<String>>,
+	pending: Pending,
+	reader: thread::JoinHandle<io::Result<()>>
+}
+
+impl Peer {
+	/// Starts reading from `reader` and writing to `writer`.
+	///
+	/// Connection has its own `Session`, which is passed to `meta`
+	/// to create metadata of incoming requests. The session is closed
+	/// once the other side closes the stream.
+	pub fn start<M, R, W, F>(handler: Arc<MetaIoHandler<M>>, framing: Framing, reader: R, writer: W, meta: F) -> Self where
+		M: Metadata,
+		R: Read + Send + 'static,
+		W: Write + Send + 'static,
+		F: FnOnce(Arc<Session>) -> M {
+		let (tx, rx) = mpsc::channel::<String>();
+		thread::spawn(move || write_messages(framing, writer, rx));
+
+		let (requests_tx, requests_rx) = mpsc::channel::<Result<Request, String>>();
+		let session = Arc::new(Session::new(tx.clone()));
+		let meta = meta(session.clone());
+		let out = tx.clone();
+		thread::spawn(move || {
+			for request in requests_rx.iter() {
+				let handler = handler.clone();
+				let meta = meta.clone();
+				let out = out.clone();
+				thread::spawn(move || match request {
+					Ok(request) => handler.handle_rpc_request_async(request, meta, move |response| {
+						if let Some(response) = response {
+							// this should never fail
+							let response = serde_json::to_string(&response).unwrap();
+							// fails only if the connection is already closed
+							let _ = out.send(response);
+						}
+					}),
+					// handler explains why the message can't be parsed
+					Err(message) => handler.handle_request_async_with_meta(&message, meta, move |response| {
+						if let Some(response) = response {
+							let _ = out.send(response);
+						}
+					})
+				});
+			}
+		});
+
+		let pending = Arc::new(Mutex::new(Some(RequestManager::new())));
+		let responses = pending.clone();
+		let out = tx.clone();
+		let reader = thread::spawn(move || {
+			let result = read_messages(framing, reader, &requests_tx, &out, &responses);
+			// pending calls fail once their senders are dropped
+			*responses.lock().unwrap() = None;
+			session.close();
+			result
+		});
+
+		Peer {
+			sender: Mutex::new(tx),
+			pending: pending,
+			reader: reader
+		}
+	}
+
+	/// Calls method of the other side and waits for its result.
+	pub fn call(&self, method: &str, params: Params) -> io::Result<Result<Value, Error>> {
+		let (tx, rx) = mpsc::channel();
+		let call = match *self.pending.lock().unwrap() {
+			Some(ref mut manager) => manager.method_call(method, params, tx),
+			None => return Err(closed())
+		};
+		try!(self.send(Request::Single(Call::MethodCall(call))));
+		rx.recv().map_err(|_| closed())
+	}
+
+	/// Sends notification to the other side.
+	pub fn notify(&self, method: &str, params: Params) -> io::Result<()> {
+		self.send(Request::Single(Call::Notification(Notification::new(method, params))))
+	}
+
+	/// Waits until the other side closes the stream.
+	pub fn join(self) -> io::Result<()> {
+		self.reader.join().unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "reader panicked")))
+	}
+
+	fn send(&self, request: Request) -> io::Result<()> {
+		self.sender.lock().unwrap().send(write_request(&request)).map_err(|_| closed())
+	}
+}
+
+fn closed() -> io::Error {
+	io::Error::new(io::ErrorKind::BrokenPipe, "connection closed")
+}
+
+/// Returns true if message is a response, single or batch.
+fn is_response(message: &Value) -> bool {
+	fn is_output(object: &BTreeMap<String, Value>) -> bool {
+		!object.contains_key("method") && (object.contains_key("result") || object.contains_key("error"))
+	}
+
+	match *message {
+		Value::Object(ref object) => is_output(object),
+		Value::Array(ref values) => match values.first() {
+			Some(&Value::Object(ref object)) => is_output(object),
+			_ => false
+		},
+		_ => false
+	}
+}
+
+fn read_messages<R>(framing: Framing, mut reader: R, requests: &mpsc::Sender<Result<Request, String>>, out: &mpsc::Sender<String>, pending: &Pending) -> io::Result<()> where R: Read {
+	let mut splitter = JsonSplitter::new();
+	let mut decoder = FrameDecoder::new();
+	let mut buf = [0u8; 4096];
+
+	loop {
+		match reader.read(&mut buf) {
+			Ok(0) => return Ok(()),
+			Ok(read) => match framing {
+				Framing::Lines => splitter.feed(&buf[..read]),
+				Framing::ContentLength => decoder.feed(&buf[..read])
+			},
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e)
+		}
+
+		loop {
+			let message = match framing {
+				Framing::Lines => splitter.next_message(),
+				Framing::ContentLength => match decoder.next_frame() {
+					Ok(message) => message,
+					Err(_) => {
+						let _ = out.send(parse_error_response());
+						continue;
+					}
+				}
+			};
+
+			let message = match message {
+				Some(message) => message,
+				None => break
+			};
+
+			// every message is parsed once and then classified
+			let value = match serde_json::from_str::<Value>(&message) {
+				Ok(value) => value,
+				Err(_) => {
+					// fails only if the connection is already closed
+					let _ = requests.send(Err(message));
+					continue;
+				}
+			};
+
+			if !is_response(&value) {
+				let _ = requests.send(Ok(Request::from(value)));
+				continue;
+			}
+
+			if let (Ok(response), Some(manager)) = (from_value::<Response>(value), pending.lock().unwrap().as_mut()) {
+				// responses to unknown calls are ignored, so is failure with null id,
+				// since calls are made concurrently and it may answer any of them
+				for (caller, result) in manager.handle_response(response).into_iter().filter_map(|result| result.ok()) {
+					let _ = caller.send(result);
+				}
+			}
+		}
+
+		if splitter.is_too_long() {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"));
+		}
+	}
+}
+
+fn write_messages<W>(framing: Framing, mut writer: W, messages: mpsc::Receiver<String>) -> io::Result<()> where W: Write {
+	for message in messages.iter() {
+		match framing {
+			Framing::Lines => {
+				try!(writer.write_all(message.as_bytes()));
+				try!(writer.write_all(b"\n"));
+			},
+			Framing::ContentLength => try!(writer.write_all(&encode_frame(&message)))
+		}
+		try!(writer.flush());
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::net::{TcpListener, TcpStream, Shutdown};
+	use super::*;
+	use super::super::super::*;
+
+	#[test]
+	fn test_peers() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let a = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+		let (b, _) = listener.accept().unwrap();
+
+		let mut io_a = IoHandler::new();
+		io_a.add_method("name", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("a".to_owned()))
+		});
+		let mut io_b = IoHandler::new();
+		io_b.add_method("name", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("b".to_owned()))
+		});
+
+		let peer_a = Peer::start(Arc::new(io_a.into()), Framing::ContentLength, a.try_clone().unwrap(), a.try_clone().unwrap(), |_| ());
+		let peer_b = Peer::start(Arc::new(io_b.into()), Framing::ContentLength, b.try_clone().unwrap(), b.try_clone().unwrap(), |_| ());
+
+		assert_eq!(peer_a.call("name", Params::None).unwrap(), Ok(Value::String("b".to_owned())));
+		assert_eq!(peer_b.call("name", Params::None).unwrap(), Ok(Value::String("a".to_owned())));
+		assert_eq!(peer_a.call("age", Params::None).unwrap(), Err(Error::method_not_found()));
+
+		a.shutdown(Shutdown::Both).unwrap();
+		assert!(peer_b.call("name", Params::None).is_err());
+		peer_a.join().unwrap();
+		peer_b.join().unwrap();
+	}
+
+	#[test]
+	fn test_calls_back_and_forth() {
+		use std::sync::Mutex;
+
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let a = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+		let (b, _) = listener.accept().unwrap();
+
+		// handlers get their peer once it's started
+		let peer_a: Arc<Mutex<Option<Arc<Peer>>>> = Arc::new(Mutex::new(None));
+		let peer_b: Arc<Mutex<Option<Arc<Peer>>>> = Arc::new(Mutex::new(None));
+
+		let mut io_a = IoHandler::new();
+		let p = peer_a.clone();
+		io_a.add_method("ping", move |_params: Params| -> Result<Value, Error> {
+			let peer = p.lock().unwrap().clone().unwrap();
+			peer.call("name", Params::None).unwrap()
+		});
+		let mut io_b = IoHandler::new();
+		io_b.add_method("name", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("b".to_owned()))
+		});
+		let p = peer_b.clone();
+		io_b.add_method("relay", move |_params: Params| -> Result<Value, Error> {
+			let peer = p.lock().unwrap().clone().unwrap();
+			peer.call("ping", Params::None).unwrap()
+		});
+
+		*peer_a.lock().unwrap() = Some(Arc::new(Peer::start(Arc::new(io_a.into()), Framing::Lines, a.try_clone().unwrap(), a.try_clone().unwrap(), |_| ())));
+		*peer_b.lock().unwrap() = Some(Arc::new(Peer::start(Arc::new(io_b.into()), Framing::Lines, b.try_clone().unwrap(), b.try_clone().unwrap(), |_| ())));
+
+		// b serves "name" while its "relay" waits for a
+		let peer = peer_a.lock().unwrap().clone().unwrap();
+		assert_eq!(peer.call("relay", Params::None).unwrap(), Ok(Value::String("b".to_owned())));
+		a.shutdown(Shutdown::Both).unwrap();
+	}
+}
@@ -0,0 +1,83 @@
+//! newline-delimited jsonrpc transport
+//!
+//! Every request is a single line. Responses are written one per line,
+//! notifications produce no output.
+//!
+//! ```rust,no_run
+//! extern crate jsonrpc_core;
+//! use std::io;
+//! use jsonrpc_core::*;
+//! use jsonrpc_core::transports::stdio;
+//!
+//! fn main() {
+//! 	let mut handler = IoHandler::new();
+//! 	handler.add_method("say_hello", |_params: Params| -> Result<Value, Error> {
+//! 		Ok(Value::String("hello".to_owned()))
+//! 	});
+//!
+//! 	let stdin = io::stdin();
+//! 	stdio::serve(&handler, stdin.lock(), io::stdout()).unwrap();
+//! }
+//! ```
+use std::io::{self, BufRead, Write};
+use super::super::{MetaIoHandler, Metadata};
+
+/// Handles every line read from `reader` with default metadata
+/// until end of input.
+pub fn serve<M, R, W>(handler: &MetaIoHandler<M>, reader: R, writer: W) -> io::Result<()> where
+	M: Metadata,
+	R: BufRead,
+	W: Write {
+	serve_with_meta(handler, reader, writer, M::default())
+}
+
+/// Handles every line read from `reader` with given metadata
+/// until end of input.
+pub fn serve_with_meta<M, R, W>(handler: &MetaIoHandler<M>, reader: R, mut writer: W, meta: M) -> io::Result<()> where
+	M: Metadata,
+	R: BufRead,
+	W: Write {
+	for line in reader.lines() {
+		let line = try!(line);
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		if let Some(response) = handler.handle_request_with_meta(&line, meta.clone()) {
+			try!(writeln!(writer, "{}", response));
+			try!(writer.flush());
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use super::*;
+	use super::super::super::*;
+
+	#[test]
+	fn test_serve() {
+		let mut handler = IoHandler::new();
+		handler.add_method("say_hello", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("hello".to_owned()))
+		});
+		handler.add_notification("log", |_params: Params| {});
+
+		let input = concat!(
+			r#"{"jsonrpc": "2.0", "method": "say_hello", "id": 1}"#, "\n",
+			"\n",
+			r#"{"jsonrpc": "2.0", "method": "log"}"#, "\n",
+			r#"{"jsonrpc": "2.0", "method": "say_hello", "id": 2}"#
+		);
+		let mut output = Vec::new();
+		serve(&handler, Cursor::new(input.as_bytes()), &mut output).unwrap();
+
+		assert_eq!(String::from_utf8(output).unwrap(), concat!(
+			r#"{"jsonrpc":"2.0","result":"hello","id":1}"#, "\n",
+			r#"{"jsonrpc":"2.0","result":"hello","id":2}"#, "\n"
+		));
+	}
+}
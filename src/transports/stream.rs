@@ -0,0 +1,234 @@
+//! streaming jsonrpc connections
+use std::{io, thread};
+use std::io::{Read, Write};
+use std::sync::{Arc, mpsc};
+use super::super::{MetaIoHandler, Metadata};
+use super::super::pubsub::Session;
+
+/// Messages longer than this close the connection.
+pub const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// Splits byte stream into messages.
+///
+/// Messages may be separated by newlines or simply concatenated,
+/// a message ends when its top-level object or array is closed.
+/// Anything else is treated as a single line message.
+///
+/// Messages must not span lines. Object or array still open at the end
+/// of the line is returned as it is, so it's answered with `ParseError`
+/// and a broken message doesn't swallow the ones after it.
+pub struct JsonSplitter {
+	buffer: Vec<u8>,
+	pos: usize,
+	depth: usize,
+	in_str: bool,
+	escaped: bool
+}
+
+impl JsonSplitter {
+	pub fn new() -> Self {
+		JsonSplitter {
+			buffer: Vec::new(),
+			pos: 0,
+			depth: 0,
+			in_str: false,
+			escaped: false
+		}
+	}
+
+	/// Appends bytes read from the stream.
+	pub fn feed(&mut self, data: &[u8]) {
+		self.buffer.extend(data.iter().cloned());
+	}
+
+	/// Returns true if incomplete message is already longer than `MAX_MESSAGE_LEN`.
+	/// Such message is never returned, so the stream should be closed.
+	pub fn is_too_long(&self) -> bool {
+		self.buffer.len() > MAX_MESSAGE_LEN
+	}
+
+	/// Returns next complete message, or `None` if more data is needed.
+	pub fn next_message(&mut self) -> Option<String> {
+		if self.pos == 0 {
+			let whitespace = self.buffer.iter().take_while(|b| is_whitespace(**b)).count();
+			self.buffer.drain(..whitespace);
+		}
+
+		let structured = match self.buffer.first() {
+			Some(&b'{') | Some(&b'[') => true,
+			Some(_) => false,
+			None => return None
+		};
+
+		while self.pos < self.buffer.len() {
+			let b = self.buffer[self.pos];
+			self.pos += 1;
+
+			if b == b'\n' {
+				return Some(self.take());
+			}
+
+			if !structured {
+				continue;
+			}
+
+			if self.in_str {
+				match b {
+					_ if self.escaped => self.escaped = false,
+					b'\\' => self.escaped = true,
+					b'"' => self.in_str = false,
+					_ => {}
+				}
+				continue;
+			}
+
+			match b {
+				b'"' => self.in_str = true,
+				b'{' | b'[' => self.depth += 1,
+				b'}' | b']' => {
+					self.depth -= 1;
+					if self.depth == 0 {
+						return Some(self.take());
+					}
+				},
+				_ => {}
+			}
+		}
+
+		None
+	}
+
+	fn take(&mut self) -> String {
+		let message: Vec<u8> = self.buffer.drain(..self.pos).collect();
+		self.pos = 0;
+		self.depth = 0;
+		self.in_str = false;
+		self.escaped = false;
+		String::from_utf8_lossy(&message).into_owned()
+	}
+}
+
+fn is_whitespace(b: u8) -> bool {
+	b == b' ' || b == b'\t' || b == b'\r' || b == b'\n'
+}
+
+/// Serves single connection until `reader` is closed
+/// or sends a message longer than `MAX_MESSAGE_LEN`.
+///
+/// Every connection has its own `Session`, which is passed to `meta`
+/// to create metadata of the connection. Responses and notifications
+/// are written to `writer` one per line by a separate thread,
+/// so asynchronous methods don't hold the connection. The session is closed with the connection, even if something still holds it.
+pub fn serve_connection<M, R, W, F>(handler: &MetaIoHandler<M>, mut reader: R, writer: W, meta: F) -> io::Result<()> where
+	M: Metadata,
+	R: Read,
+	W: Write + Send + 'static,
+	F: FnOnce(Arc<Session>) -> M {
+	let (tx, rx) = mpsc::channel::<String>();
+	let writer_thread = thread::spawn(move || -> io::Result<()> {
+		let mut writer = writer;
+		for message in rx.iter() {
+			try!(writer.write_all(message.as_bytes()));
+			try!(writer.write_all(b"\n"));
+			try!(writer.flush());
+		}
+		Ok(())
+	});
+
+	let session = Arc::new(Session::new(tx.clone()));
+	let meta = meta(session.clone());
+	let result = read_messages(handler, &mut reader, &tx, &meta);
+
+	// writer finishes once every sender is gone, including the one of the session
+	// and those waiting for asynchronous responses
+	session.close();
+	drop(meta);
+	drop(tx);
+	let written = writer_thread.join().unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "writer panicked")));
+	result.and(written)
+}
+
+fn read_messages<M, R>(handler: &MetaIoHandler<M>, reader: &mut R, tx: &mpsc::Sender<String>, meta: &M) -> io::Result<()> where
+	M: Metadata,
+	R: Read {
+	let mut splitter = JsonSplitter::new();
+	let mut buf = [0u8; 4096];
+
+	loop {
+		match reader.read(&mut buf) {
+			Ok(0) => return Ok(()),
+			Ok(read) => splitter.feed(&buf[..read]),
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e)
+		}
+
+		while let Some(message) = splitter.next_message() {
+			let tx = tx.clone();
+			handler.handle_request_async_with_meta(&message, meta.clone(), move |response| {
+				if let Some(response) = response {
+					// fails only if the connection is already closed
+					let _ = tx.send(response);
+				}
+			});
+		}
+
+		if splitter.is_too_long() {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_json_splitter() {
+		let mut splitter = JsonSplitter::new();
+		splitter.feed(b"  {\"a\": \"}\\\"\"}[1, [2]]\n{\"b\"");
+		assert_eq!(splitter.next_message(), Some(r#"{"a": "}\""}"#.to_owned()));
+		assert_eq!(splitter.next_message(), Some("[1, [2]]".to_owned()));
+		assert_eq!(splitter.next_message(), None);
+		splitter.feed(b": 1}\nnot json\n");
+		assert_eq!(splitter.next_message(), Some(r#"{"b": 1}"#.to_owned()));
+		assert_eq!(splitter.next_message(), Some("not json\n".to_owned()));
+		assert_eq!(splitter.next_message(), None);
+
+		// unclosed message ends with its line
+		splitter.feed(b"{\"a\": [1, \"x\n{\"b\": 2}\n");
+		assert_eq!(splitter.next_message(), Some("{\"a\": [1, \"x\n".to_owned()));
+		assert_eq!(splitter.next_message(), Some(r#"{"b": 2}"#.to_owned()));
+		assert_eq!(splitter.next_message(), None);
+	}
+
+	#[test]
+	fn test_message_too_long() {
+		use std::io;
+		use std::io::Cursor;
+		use super::super::super::*;
+
+		let mut splitter = JsonSplitter::new();
+		splitter.feed(&vec![b'['; MAX_MESSAGE_LEN]);
+		assert_eq!(splitter.next_message(), None);
+		assert!(!splitter.is_too_long());
+		splitter.feed(b"[");
+		assert!(splitter.is_too_long());
+
+		// connection is closed before the line ends
+		let input = vec![b'x'; MAX_MESSAGE_LEN + 1];
+		let result = serve_connection(&MetaIoHandler::<()>::new(), Cursor::new(input), Vec::new(), |_| ());
+		assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn test_session_outlives_connection() {
+		use std::io::Cursor;
+		use super::super::super::*;
+
+		let mut session = None;
+		serve_connection(&MetaIoHandler::<()>::new(), Cursor::new(vec![]), Vec::new(), |s| session = Some(s)).unwrap();
+		let session = session.unwrap();
+		assert!(session.is_closed());
+		assert_eq!(session.send("pushed".to_owned()), Err(pubsub::SessionClosed));
+	}
+}
@@ -0,0 +1,119 @@
+//! tcp jsonrpc server
+use std::io;
+use std::net::{TcpListener, TcpStream, SocketAddr};
+use std::sync::Arc;
+use super::Listening;
+use super::stream::serve_connection;
+use super::super::{MetaIoHandler, Metadata};
+use super::super::pubsub::Session;
+
+/// Tcp server with a session per connection.
+///
+/// Messages are separated by newlines or simply concatenated and must not
+/// span lines, responses are written one per line.
+///
+/// ```rust
+/// extern crate jsonrpc_core;
+/// use std::sync::Arc;
+/// use std::io::{BufRead, BufReader, Write};
+/// use std::net::TcpStream;
+/// use jsonrpc_core::*;
+/// use jsonrpc_core::transports::tcp::Server;
+///
+/// fn main() {
+/// 	let mut io = IoHandler::new();
+/// 	io.add_method("say_hello", |_params: Params| -> Result<Value, Error> {
+/// 		Ok(Value::String("hello".to_owned()))
+/// 	});
+///
+/// 	let server = Server::start(&"127.0.0.1:0".parse().unwrap(), Arc::new(io.into()), |_, _| ()).unwrap();
+///
+/// 	let mut client = TcpStream::connect(server.local_addr()).unwrap();
+/// 	client.write_all(br#"{"jsonrpc": "2.0", "method": "say_hello", "id": 1}"#).unwrap();
+///
+/// 	let mut response = String::new();
+/// 	BufReader::new(client).read_line(&mut response).unwrap();
+/// 	assert_eq!(response, "{\"jsonrpc\":\"2.0\",\"result\":\"hello\",\"id\":1}\n");
+///
+/// 	server.close();
+/// }
+/// ```
+pub struct Server {
+	local_addr: SocketAddr,
+	listening: Listening
+}
+
+impl Server {
+	/// Starts listening on given address.
+	///
+	/// `meta` creates metadata of every connection from its peer address and session.
+	pub fn start<M, F>(addr: &SocketAddr, handler: Arc<MetaIoHandler<M>>, meta: F) -> io::Result<Self> where
+		M: Metadata,
+		F: Fn(&SocketAddr, Arc<Session>) -> M + Send + Sync + 'static {
+		let listener = try!(TcpListener::bind(addr));
+		let local_addr = try!(listener.local_addr());
+		let listening = try!(Listening::start(listener, move |stream: TcpStream, writer| {
+			let peer = match stream.peer_addr() {
+				Ok(peer) => peer,
+				Err(_) => return
+			};
+			let _ = serve_connection(&handler, stream, writer, |session| (*meta)(&peer, session));
+		}));
+
+		Ok(Server {
+			local_addr: local_addr,
+			listening: listening
+		})
+	}
+
+	/// Address the server is listening on.
+	pub fn local_addr(&self) -> &SocketAddr {
+		&self.local_addr
+	}
+
+	/// Stops accepting connections and waits until every connection is closed.
+	pub fn close(mut self) {
+		self.listening.stop()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::io::{BufRead, BufReader, Write, Read};
+	use std::net::TcpStream;
+	use super::*;
+	use super::super::super::*;
+
+	#[test]
+	fn test_tcp_server() {
+		let mut io = IoHandler::new();
+		io.add_method("say_hello", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("hello".to_owned()))
+		});
+
+		let server = Server::start(&"127.0.0.1:0".parse().unwrap(), Arc::new(io.into()), |_, _| ()).unwrap();
+		let mut client = TcpStream::connect(server.local_addr()).unwrap();
+		client.write_all(concat!(
+			r#"{"jsonrpc": "2.0", "method": "say_hello", "id": 1}"#, "\n",
+			r#"{"jsonrpc": "2.0", "method": "say_hello", "id": 2}{"jsonrpc": "2.0", "method": "say_hello"}"#,
+			"garbage\n"
+		).as_bytes()).unwrap();
+
+		let mut reader = BufReader::new(client.try_clone().unwrap());
+		let mut lines = vec![String::new(), String::new(), String::new()];
+		for line in &mut lines {
+			reader.read_line(line).unwrap();
+		}
+		assert_eq!(lines, vec![
+			"{\"jsonrpc\":\"2.0\",\"result\":\"hello\",\"id\":1}\n".to_owned(),
+			"{\"jsonrpc\":\"2.0\",\"result\":\"hello\",\"id\":2}\n".to_owned(),
+			"{\"jsonrpc\":\"2.0\",\"error\":{\"code\":-32700,\"message\":\"Parse error.\",\"data\":null},\"id\":null}\n".to_owned()
+		]);
+
+		// graceful shutdown closes the connection
+		server.close();
+		let mut rest = String::new();
+		assert_eq!(reader.read_to_string(&mut rest).unwrap(), 0);
+	}
+}
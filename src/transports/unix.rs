@@ -0,0 +1,234 @@
+//! unix domain socket jsonrpc server and client
+use std::{io, fs};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::{Listening, handle_message};
+use super::stream::serve_connection;
+use super::super::{MetaIoHandler, Metadata, Params, Value, Error, Request, Call, Notification};
+use super::super::client::{RequestManager, write_request};
+use super::super::pubsub::Session;
+
+/// Unix socket server with a session per connection.
+///
+/// Messages are separated by newlines or simply concatenated and must not
+/// span lines, responses are written one per line.
+pub struct Server {
+	path: PathBuf,
+	/// Device and inode of the socket file, so only this file is removed on close.
+	file_id: (u64, u64),
+	listening: Option<Listening>
+}
+
+impl Server {
+	/// Starts listening on socket file at given path with given permissions, e.g. `0o600`.
+	///
+	/// Socket file left behind by a server which is no longer running is removed.
+	/// `meta` creates metadata of every connection from its session.
+	pub fn start<M, F, P>(path: P, mode: u32, handler: Arc<MetaIoHandler<M>>, meta: F) -> io::Result<Self> where
+		M: Metadata,
+		F: Fn(Arc<Session>) -> M + Send + Sync + 'static,
+		P: AsRef<Path> {
+		let path = path.as_ref().to_path_buf();
+		try!(remove_stale_socket(&path));
+		let listener = try!(bind_with_mode(&path, mode));
+		let file_id = match fs::symlink_metadata(&path) {
+			Ok(meta) => (meta.dev(), meta.ino()),
+			Err(e) => {
+				let _ = fs::remove_file(&path);
+				return Err(e);
+			}
+		};
+		let listening = Listening::start(listener, move |stream: UnixStream, writer| {
+			let _ = serve_connection(&handler, stream, writer, |session| (*meta)(session));
+		});
+		let listening = match listening {
+			Ok(listening) => listening,
+			Err(e) => {
+				let _ = fs::remove_file(&path);
+				return Err(e);
+			}
+		};
+
+		Ok(Server {
+			path: path,
+			file_id: file_id,
+			listening: Some(listening)
+		})
+	}
+
+	/// Path of the socket file.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Stops accepting connections, waits until every connection is closed
+	/// and removes the socket file, unless it was replaced meanwhile.
+	pub fn close(mut self) {
+		self.stop()
+	}
+
+	fn stop(&mut self) {
+		if let Some(mut listening) = self.listening.take() {
+			listening.stop();
+			match fs::symlink_metadata(&self.path) {
+				Ok(ref meta) if (meta.dev(), meta.ino()) == self.file_id => {
+					let _ = fs::remove_file(&self.path);
+				},
+				_ => {}
+			}
+		}
+	}
+}
+
+impl Drop for Server {
+	fn drop(&mut self) {
+		self.stop()
+	}
+}
+
+/// Binds socket with given permissions.
+///
+/// Socket is created in a private directory and linked to `path`
+/// once its permissions are set, so it's never accessible with
+/// the permissions given by umask.
+fn bind_with_mode(path: &Path, mode: u32) -> io::Result<UnixListener> {
+	let name = try!(path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name")));
+	let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+	let mut dir_name = name.to_os_string();
+	dir_name.push(format!(".{}.tmp", nanos));
+	let dir = path.with_file_name(dir_name);
+	try!(fs::DirBuilder::new().mode(0o700).create(&dir));
+
+	let tmp_path = dir.join(name);
+	let result = UnixListener::bind(&tmp_path).and_then(|listener| {
+		try!(fs::set_permissions(&tmp_path, fs::Permissions::from_mode(mode)));
+		// unlike rename, linking fails if the path is taken meanwhile
+		try!(fs::hard_link(&tmp_path, path));
+		Ok(listener)
+	});
+
+	let _ = fs::remove_file(&tmp_path);
+	let _ = fs::remove_dir(&dir);
+	result
+}
+
+/// Removes socket file at given path, unless a server is still accepting on it.
+///
+/// Only socket which refuses connections is removed. Any other error,
+/// e.g. missing permission to connect, is returned.
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+	match fs::symlink_metadata(path) {
+		Ok(ref meta) if meta.file_type().is_socket() => {},
+		Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, "path exists and is not a socket")),
+		Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+		Err(e) => return Err(e)
+	}
+
+	match UnixStream::connect(path) {
+		Ok(_) => Err(io::Error::new(io::ErrorKind::AddrInUse, "socket is already in use")),
+		Err(ref e) if e.kind() == io::ErrorKind::ConnectionRefused => fs::remove_file(path),
+		Err(e) => Err(e)
+	}
+}
+
+/// Blocking unix socket client.
+///
+/// Notifications received while waiting for a response are kept
+/// until they are taken with `take_notifications`.
+pub struct Client {
+	writer: UnixStream,
+	reader: BufReader<UnixStream>,
+	manager: RequestManager<()>,
+	notifications: Vec<Notification>
+}
+
+impl Client {
+	/// Connects to the socket file at given path.
+	pub fn connect<P>(path: P) -> io::Result<Self> where P: AsRef<Path> {
+		let writer = try!(UnixStream::connect(path));
+		let reader = try!(writer.try_clone());
+		Ok(Client {
+			writer: writer,
+			reader: BufReader::new(reader),
+			manager: RequestManager::new(),
+			notifications: Vec::new()
+		})
+	}
+
+	/// Calls method and waits for its result.
+	pub fn call(&mut self, method: &str, params: Params) -> io::Result<Result<Value, Error>> {
+		let call = self.manager.method_call(method, params, ());
+		let id = call.id.clone();
+		try!(self.send(Request::Single(Call::MethodCall(call))));
+
+		loop {
+			let mut line = String::new();
+			if try!(self.reader.read_line(&mut line)) == 0 {
+				return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+			}
+
+			if let Some(result) = handle_message(&mut self.manager, &mut self.notifications, Some(&id), &line) {
+				return Ok(result);
+			}
+		}
+	}
+
+	/// Sends notification.
+	pub fn notify(&mut self, method: &str, params: Params) -> io::Result<()> {
+		self.send(Request::Single(Call::Notification(Notification::new(method, params))))
+	}
+
+	/// Returns notifications received so far.
+	pub fn take_notifications(&mut self) -> Vec<Notification> {
+		self.notifications.drain(..).collect()
+	}
+
+	fn send(&mut self, request: Request) -> io::Result<()> {
+		try!(self.writer.write_all(write_request(&request).as_bytes()));
+		try!(self.writer.write_all(b"\n"));
+		self.writer.flush()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+	use std::fs;
+	use std::os::unix::fs::PermissionsExt;
+	use std::os::unix::net::UnixListener;
+	use std::sync::Arc;
+	use std::time::{SystemTime, UNIX_EPOCH};
+	use super::*;
+	use super::super::super::*;
+
+	#[test]
+	fn test_unix_server_and_client() {
+		// unique path, so concurrent runs don't share the socket
+		let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+		let path = env::temp_dir().join(format!("jsonrpc-core-test-unix-{}.sock", nanos));
+		// stale socket file is removed on start
+		drop(UnixListener::bind(&path));
+
+		let mut io = IoHandler::new();
+		io.add_method("say_hello", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("hello".to_owned()))
+		});
+
+		let server = Server::start(&path, 0o600, Arc::new(io.into()), |_| ()).unwrap();
+		assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+		assert!(Server::start(&path, 0o600, Arc::new(IoHandler::new().into()), |_| ()).is_err());
+
+		let mut client = Client::connect(&path).unwrap();
+		client.notify("say_hello", Params::None).unwrap();
+		assert_eq!(client.call("say_hello", Params::None).unwrap(), Ok(Value::String("hello".to_owned())));
+		assert_eq!(client.call("say_bye", Params::None).unwrap(), Err(Error::method_not_found()));
+
+		server.close();
+		assert!(!path.exists());
+		assert!(client.call("say_hello", Params::None).is_err());
+	}
+}
@@ -0,0 +1,375 @@
+//! websocket jsonrpc server
+//!
+//! Every text message is a request. Responses and notifications pushed
+//! through the connection `Session` are sent back as text messages.
+use std::{io, thread};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, SocketAddr};
+use std::sync::{Arc, Mutex, mpsc};
+use super::{Listening, read_head, Head, HEADERS_END};
+use super::super::{MetaIoHandler, Metadata};
+use super::super::pubsub::Session;
+
+/// Messages longer than this close the connection.
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+const ACCEPT_GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC11B85";
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// Websocket server with a session per socket.
+///
+/// `Session` of the socket is passed to `meta`, so server code can keep it
+/// and push notifications to the client, e.g. with `pubsub::Sink`.
+/// The session is closed once the socket is closed.
+pub struct Server {
+	local_addr: SocketAddr,
+	listening: Listening
+}
+
+impl Server {
+	/// Starts listening on given address.
+	///
+	/// `meta` creates metadata of every socket from its peer address and session.
+	pub fn start<M, F>(addr: &SocketAddr, handler: Arc<MetaIoHandler<M>>, meta: F) -> io::Result<Self> where
+		M: Metadata,
+		F: Fn(&SocketAddr, Arc<Session>) -> M + Send + Sync + 'static {
+		let listener = try!(TcpListener::bind(addr));
+		let local_addr = try!(listener.local_addr());
+		let listening = try!(Listening::start(listener, move |stream: TcpStream, writer| {
+			let peer = match stream.peer_addr() {
+				Ok(peer) => peer,
+				Err(_) => return
+			};
+			let _ = serve_socket(&handler, stream, writer, |session| (*meta)(&peer, session));
+		}));
+
+		Ok(Server {
+			local_addr: local_addr,
+			listening: listening
+		})
+	}
+
+	/// Address the server is listening on.
+	pub fn local_addr(&self) -> &SocketAddr {
+		&self.local_addr
+	}
+
+	/// Stops accepting connections and waits until every socket is closed.
+	pub fn close(mut self) {
+		self.listening.stop()
+	}
+}
+
+/// Performs opening handshake and serves the socket until it's closed.
+fn serve_socket<M, R, W, F>(handler: &MetaIoHandler<M>, mut reader: R, mut writer: W, meta: F) -> io::Result<()> where
+	M: Metadata,
+	R: Read,
+	W: Write + Send + 'static,
+	F: FnOnce(Arc<Session>) -> M {
+	let mut buffer = vec![];
+	let head_len = match try!(read_head(&mut reader, &mut buffer)) {
+		Some(len) => len,
+		None => return Ok(())
+	};
+
+	let key = match Head::parse(&buffer[..head_len]).and_then(|head| handshake_key(&head)) {
+		Some(key) => key,
+		None => {
+			try!(writer.write_all(b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\nContent-Length: 0\r\n\r\n"));
+			return writer.flush();
+		}
+	};
+
+	try!(write!(writer, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept_key(&key)));
+	try!(writer.flush());
+
+	// frames sent right after the handshake may be already read
+	let rest = buffer.split_off(head_len + HEADERS_END.len());
+	let mut reader = io::Cursor::new(rest).chain(reader);
+
+	let writer = Arc::new(Mutex::new(writer));
+	let (tx, rx) = mpsc::channel::<String>();
+	let out = writer.clone();
+	let writer_thread = thread::spawn(move || -> io::Result<()> {
+		for message in rx.iter() {
+			try!(write_frame(&mut *out.lock().unwrap(), OPCODE_TEXT, message.as_bytes()));
+		}
+		Ok(())
+	});
+
+	let session = Arc::new(Session::new(tx.clone()));
+	let meta = meta(session.clone());
+	let result = read_messages(handler, &mut reader, &writer, &tx, &meta);
+
+	// writer finishes once every sender is gone, including the one of the session
+	// and those waiting for asynchronous responses
+	session.close();
+	drop(meta);
+	drop(tx);
+	let written = writer_thread.join().unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "writer panicked")));
+	// normal closure, fails if the peer is already gone
+	let _ = write_frame(&mut *writer.lock().unwrap(), OPCODE_CLOSE, &[0x03, 0xe8]);
+	result.and(written)
+}
+
+fn read_messages<M, R, W>(handler: &MetaIoHandler<M>, reader: &mut R, writer: &Mutex<W>, tx: &mpsc::Sender<String>, meta: &M) -> io::Result<()> where
+	M: Metadata,
+	R: Read,
+	W: Write {
+	let mut message = vec![];
+
+	loop {
+		let frame = match try!(read_frame(reader)) {
+			Some(frame) => frame,
+			None => return Ok(())
+		};
+
+		match frame.opcode {
+			OPCODE_CONTINUATION | OPCODE_TEXT | OPCODE_BINARY => {
+				if message.len() + frame.payload.len() > MAX_MESSAGE_LEN {
+					return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"));
+				}
+				message.extend(frame.payload);
+				if frame.fin {
+					let request = String::from_utf8_lossy(&message).into_owned();
+					message.clear();
+					let tx = tx.clone();
+					handler.handle_request_async_with_meta(&request, meta.clone(), move |response| {
+						if let Some(response) = response {
+							// fails only if the connection is already closed
+							let _ = tx.send(response);
+						}
+					});
+				}
+			},
+			OPCODE_PING => try!(write_frame(&mut *writer.lock().unwrap(), OPCODE_PONG, &frame.payload)),
+			OPCODE_CLOSE => return Ok(()),
+			// pongs and unknown frames are ignored
+			_ => {}
+		}
+	}
+}
+
+/// Returns `Sec-WebSocket-Key` of valid upgrade request.
+fn handshake_key(head: &Head) -> Option<String> {
+	let upgrade = head.header("upgrade").map(|value| value.eq_ignore_ascii_case("websocket")).unwrap_or(false);
+	match (&head.method as &str, upgrade) {
+		("GET", true) => head.header("sec-websocket-key").map(|key| key.to_owned()),
+		_ => None
+	}
+}
+
+/// Computes `Sec-WebSocket-Accept` for given key.
+fn accept_key(key: &str) -> String {
+	base64(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()))
+}
+
+struct Frame {
+	fin: bool,
+	opcode: u8,
+	payload: Vec<u8>
+}
+
+/// Reads single frame, or returns `None` if the stream is closed.
+fn read_frame<R>(reader: &mut R) -> io::Result<Option<Frame>> where R: Read {
+	let mut header = [0u8; 2];
+	match reader.read_exact(&mut header) {
+		Ok(()) => {},
+		Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+		Err(e) => return Err(e)
+	}
+
+	let len = match header[1] & 0x7f {
+		126 => {
+			let mut len = [0u8; 2];
+			try!(reader.read_exact(&mut len));
+			len.iter().fold(0u64, |acc, b| acc << 8 | *b as u64)
+		},
+		127 => {
+			let mut len = [0u8; 8];
+			try!(reader.read_exact(&mut len));
+			len.iter().fold(0u64, |acc, b| acc << 8 | *b as u64)
+		},
+		len => len as u64
+	};
+	if len > MAX_MESSAGE_LEN as u64 {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too long"));
+	}
+
+	let mut mask = [0u8; 4];
+	let masked = header[1] & 0x80 != 0;
+	if masked {
+		try!(reader.read_exact(&mut mask));
+	}
+
+	let mut payload = vec![0u8; len as usize];
+	try!(reader.read_exact(&mut payload));
+	if masked {
+		for (i, b) in payload.iter_mut().enumerate() {
+			*b ^= mask[i % 4];
+		}
+	}
+
+	Ok(Some(Frame {
+		fin: header[0] & 0x80 != 0,
+		opcode: header[0] & 0x0f,
+		payload: payload
+	}))
+}
+
+/// Writes single unmasked frame.
+fn write_frame<W>(writer: &mut W, opcode: u8, payload: &[u8]) -> io::Result<()> where W: Write {
+	let mut frame = vec![0x80 | opcode];
+	match payload.len() {
+		len if len < 126 => frame.push(len as u8),
+		len if len <= 0xffff => {
+			frame.push(126);
+			frame.extend((0..2).rev().map(|i| (len >> (i * 8)) as u8));
+		},
+		len => {
+			frame.push(127);
+			frame.extend((0..8).rev().map(|i| ((len as u64) >> (i * 8)) as u8));
+		}
+	}
+	frame.extend(payload.iter().cloned());
+	try!(writer.write_all(&frame));
+	writer.flush()
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+	let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+	let mut message = data.to_vec();
+	message.push(0x80);
+	while message.len() % 64 != 56 {
+		message.push(0);
+	}
+	let bits = (data.len() as u64) * 8;
+	message.extend((0..8).rev().map(|i| (bits >> (i * 8)) as u8));
+
+	for block in message.chunks(64) {
+		let mut w = [0u32; 80];
+		for i in 0..16 {
+			w[i] = (block[i * 4] as u32) << 24 | (block[i * 4 + 1] as u32) << 16 | (block[i * 4 + 2] as u32) << 8 | block[i * 4 + 3] as u32;
+		}
+		for i in 16..80 {
+			w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+		}
+
+		let (mut a, mut b, mut c, mut d, mut e) = (h[0], h[1], h[2], h[3], h[4]);
+		for i in 0..80 {
+			let (f, k) = match i {
+				0...19 => ((b & c) | (!b & d), 0x5a827999),
+				20...39 => (b ^ c ^ d, 0x6ed9eba1),
+				40...59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+				_ => (b ^ c ^ d, 0xca62c1d6)
+			};
+			let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(w[i]);
+			e = d;
+			d = c;
+			c = b.rotate_left(30);
+			b = a;
+			a = temp;
+		}
+
+		h[0] = h[0].wrapping_add(a);
+		h[1] = h[1].wrapping_add(b);
+		h[2] = h[2].wrapping_add(c);
+		h[3] = h[3].wrapping_add(d);
+		h[4] = h[4].wrapping_add(e);
+	}
+
+	let mut digest = [0u8; 20];
+	for (i, word) in h.iter().enumerate() {
+		for j in 0..4 {
+			digest[i * 4 + j] = (word >> (24 - j * 8)) as u8;
+		}
+	}
+	digest
+}
+
+fn base64(data: &[u8]) -> String {
+	const CHARS: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut encoded = String::new();
+
+	for chunk in data.chunks(3) {
+		let n = chunk.iter().enumerate().fold(0u32, |acc, (i, b)| acc | (*b as u32) << (16 - i * 8));
+		for i in 0..4 {
+			if i <= chunk.len() {
+				encoded.push(CHARS[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+			} else {
+				encoded.push('=');
+			}
+		}
+	}
+	encoded
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex};
+	use std::io::{BufRead, BufReader, Write};
+	use std::net::TcpStream;
+	use super::*;
+	use super::{accept_key, read_frame, OPCODE_TEXT, OPCODE_CLOSE};
+	use super::super::super::*;
+
+	fn masked_frame(text: &str) -> Vec<u8> {
+		let mask = [1u8, 2, 3, 4];
+		let mut frame = vec![0x81, 0x80 | text.len() as u8];
+		frame.extend(mask.iter().cloned());
+		frame.extend(text.bytes().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+		frame
+	}
+
+	#[test]
+	fn test_accept_key() {
+		assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_owned());
+	}
+
+	#[test]
+	fn test_ws_server() {
+		let mut io = IoHandler::new();
+		io.add_method("say_hello", |_params: Params| -> Result<Value, Error> {
+			Ok(Value::String("hello".to_owned()))
+		});
+
+		let sessions = Arc::new(Mutex::new(vec![]));
+		let saved = sessions.clone();
+		// server code keeps the session, metadata doesn't
+		let server = Server::start(&"127.0.0.1:0".parse().unwrap(), Arc::new(io.into()), move |_, session| {
+			saved.lock().unwrap().push(session);
+		}).unwrap();
+
+		let mut client = TcpStream::connect(server.local_addr()).unwrap();
+		client.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n").unwrap();
+		client.write_all(&masked_frame(r#"{"jsonrpc": "2.0", "method": "say_hello", "id": 1}"#)).unwrap();
+
+		let mut reader = BufReader::new(client.try_clone().unwrap());
+		let mut handshake = vec![String::new(), String::new(), String::new(), String::new(), String::new()];
+		for line in &mut handshake {
+			reader.read_line(line).unwrap();
+		}
+		assert_eq!(handshake[0], "HTTP/1.1 101 Switching Protocols\r\n".to_owned());
+		assert_eq!(handshake[3], "Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n".to_owned());
+
+		let frame = read_frame(&mut reader).unwrap().unwrap();
+		assert_eq!(frame.opcode, OPCODE_TEXT);
+		assert_eq!(frame.payload, br#"{"jsonrpc":"2.0","result":"hello","id":1}"#.to_vec());
+
+		// server push
+		let session = sessions.lock().unwrap()[0].clone();
+		session.send("pushed".to_owned()).unwrap();
+		assert_eq!(read_frame(&mut reader).unwrap().unwrap().payload, b"pushed".to_vec());
+
+		// kept session doesn't stop the server from closing
+		server.close();
+		assert_eq!(read_frame(&mut reader).unwrap().unwrap().opcode, OPCODE_CLOSE);
+		assert_eq!(session.send("pushed".to_owned()), Err(pubsub::SessionClosed));
+	}
+}